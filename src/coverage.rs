@@ -0,0 +1,298 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use eyre::{eyre, Context, Result};
+use regex::Regex;
+
+/// Line and branch coverage of one source file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileCoverage {
+    /// Hit count of every instrumented line
+    pub line_hits: BTreeMap<usize, u64>,
+    pub branches_found: usize,
+    pub branches_hit: usize,
+}
+
+impl FileCoverage {
+    /// Share of instrumented lines executed at least once, between 0 and 1
+    pub fn line_rate(&self) -> f64 {
+        if self.line_hits.is_empty() {
+            return 0.0;
+        }
+        let hit = self.line_hits.values().filter(|hits| **hits > 0).count();
+        hit as f64 / self.line_hits.len() as f64
+    }
+
+    /// Share of branches taken at least once, `None` when no branch was instrumented
+    pub fn branch_rate(&self) -> Option<f64> {
+        (self.branches_found > 0).then(|| self.branches_hit as f64 / self.branches_found as f64)
+    }
+
+    /// Line rate restricted to the lines from `start` to `end`, `None` when none is instrumented
+    pub fn line_rate_between(&self, start: usize, end: usize) -> Option<f64> {
+        let lines: Vec<_> = self.line_hits.range(start..=end).collect();
+        if lines.is_empty() {
+            return None;
+        }
+        let hit = lines.iter().filter(|(_, hits)| **hits > 0).count();
+        Some(hit as f64 / lines.len() as f64)
+    }
+
+    fn merge(&mut self, other: FileCoverage) {
+        for (line, hits) in other.line_hits {
+            *self.line_hits.entry(line).or_default() += hits;
+        }
+        self.branches_found += other.branches_found;
+        self.branches_hit += other.branches_hit;
+    }
+}
+
+/// Coverage of a test run, per source file as named by the report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    files: HashMap<String, FileCoverage>,
+}
+
+impl Coverage {
+    /// Read an lcov tracefile or a Cobertura XML report, told apart by their content
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .wrap_err(format!("Unable to read coverage report {}", path.display()))?;
+
+        if content.trim_start().starts_with('<') {
+            Self::from_cobertura(&content)
+        } else {
+            Self::from_lcov(&content)
+        }
+    }
+
+    pub fn from_lcov(content: &str) -> Result<Self> {
+        let mut coverage = Self::default();
+        let mut current: Option<(String, FileCoverage)> = None;
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            let (record, value) = line.split_once(':').unwrap_or((line, ""));
+            let invalid = || eyre!("Invalid lcov record on line {}: {line}", index + 1);
+
+            match record {
+                "SF" => current = Some((value.to_string(), FileCoverage::default())),
+                "DA" => {
+                    let (_, file) = current.as_mut().ok_or_else(invalid)?;
+                    let mut fields = value.split(',');
+                    let number = fields.next().and_then(|f| f.parse().ok());
+                    let hits: Option<u64> = fields.next().and_then(|f| f.parse().ok());
+                    let (Some(number), Some(hits)) = (number, hits) else {
+                        return Err(invalid());
+                    };
+                    *file.line_hits.entry(number).or_default() += hits;
+                }
+                "BRDA" => {
+                    let (_, file) = current.as_mut().ok_or_else(invalid)?;
+                    let taken = value.rsplit(',').next().ok_or_else(invalid)?;
+                    file.branches_found += 1;
+                    if taken != "-" && taken != "0" {
+                        file.branches_hit += 1;
+                    }
+                }
+                "end_of_record" => {
+                    if let Some((name, file)) = current.take() {
+                        coverage.add(name, file);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((name, file)) = current {
+            coverage.add(name, file);
+        }
+
+        Ok(coverage)
+    }
+
+    pub fn from_cobertura(content: &str) -> Result<Self> {
+        let tag = Regex::new(r"<(/?methods|class|line)\b([^>]*)>").expect("valid regex");
+        let attribute = Regex::new(r#"([\w-]+)="([^"]*)""#).expect("valid regex");
+        let condition = Regex::new(r"\((\d+)/(\d+)\)").expect("valid regex");
+
+        let mut coverage = Self::default();
+        let mut current: Option<(String, FileCoverage)> = None;
+        let mut in_methods = false;
+
+        for captures in tag.captures_iter(content) {
+            let attributes: HashMap<&str, &str> = attribute
+                .captures_iter(&captures[2])
+                .filter_map(|c| Some((c.get(1)?.as_str(), c.get(2)?.as_str())))
+                .collect();
+
+            // A self-closing `<methods/>` opens and closes the list at once
+            let self_closing = captures[2].trim_end().ends_with('/');
+            match &captures[1] {
+                "methods" => in_methods = !self_closing,
+                "/methods" => in_methods = false,
+                "class" => {
+                    if let Some((name, file)) = current.take() {
+                        coverage.add(name, file);
+                    }
+                    let filename = attributes
+                        .get("filename")
+                        .ok_or_else(|| eyre!("Cobertura class without a filename"))?;
+                    current = Some((filename.to_string(), FileCoverage::default()));
+                }
+                _ => {}
+            }
+
+            // Method lines repeat the lines of their class
+            let Some((_, file)) = current
+                .as_mut()
+                .filter(|_| !in_methods && &captures[1] == "line")
+            else {
+                continue;
+            };
+            let number = attributes.get("number").and_then(|n| n.parse().ok());
+            let hits: Option<u64> = attributes.get("hits").and_then(|h| h.parse().ok());
+            let (Some(number), Some(hits)) = (number, hits) else {
+                return Err(eyre!("Cobertura line without a number or hits"));
+            };
+            file.line_hits.insert(number, hits);
+
+            if let Some(captures) = attributes
+                .get("condition-coverage")
+                .and_then(|c| condition.captures(c))
+            {
+                let covered: usize = captures[1].parse().unwrap_or_default();
+                let total: usize = captures[2].parse().unwrap_or_default();
+                file.branches_hit += covered;
+                file.branches_found += total;
+            }
+        }
+
+        if let Some((name, file)) = current {
+            coverage.add(name, file);
+        }
+
+        Ok(coverage)
+    }
+
+    fn add(&mut self, name: String, file: FileCoverage) {
+        self.files.entry(name).or_default().merge(file);
+    }
+
+    /// Coverage of a repository relative `filename`, matching the report paths that end with it
+    /// since reports usually hold absolute paths or paths relative to another root.
+    pub fn for_file(&self, filename: &str) -> Option<&FileCoverage> {
+        let filename = filename.replace('\\', "/");
+        self.files
+            .iter()
+            .filter(|(name, _)| {
+                let name = name.replace('\\', "/");
+                name == filename || name.ends_with(&format!("/{filename}"))
+            })
+            .min_by_key(|(name, _)| name.len())
+            .map(|(_, file)| file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Coverage;
+
+    const LCOV: &str = "TN:
+SF:/home/ci/project/src/lib.rs
+DA:1,1
+DA:2,0
+DA:3,4
+DA:4,0
+BRDA:2,0,0,1
+BRDA:2,0,1,-
+LF:4
+LH:2
+end_of_record
+SF:/home/ci/project/src/main.rs
+DA:1,1
+end_of_record
+";
+
+    const COBERTURA: &str = r#"<?xml version="1.0" ?>
+<coverage line-rate="0.5" branch-rate="0.5" version="1.9">
+  <packages>
+    <package name="project">
+      <classes>
+        <class name="lib" filename="src/lib.rs" line-rate="0.5" branch-rate="0.5">
+          <methods>
+            <method name="f"><lines><line number="1" hits="1"/></lines></method>
+          </methods>
+          <lines>
+            <line number="1" hits="1"/>
+            <line number="2" hits="0" branch="true" condition-coverage="50% (1/2)"/>
+          </lines>
+        </class>
+      </classes>
+    </package>
+  </packages>
+</coverage>"#;
+
+    #[test]
+    fn parse_lcov() {
+        let coverage = Coverage::from_lcov(LCOV).expect("lcov parsed");
+
+        let lib = coverage.for_file("src/lib.rs").expect("lib coverage");
+        assert_eq!(lib.line_rate(), 0.5);
+        assert_eq!(lib.branch_rate(), Some(0.5));
+        assert_eq!(lib.line_rate_between(3, 4), Some(0.5));
+        assert_eq!(lib.line_rate_between(10, 20), None);
+
+        assert_eq!(
+            coverage.for_file("src/main.rs").map(|f| f.line_rate()),
+            Some(1.0)
+        );
+        assert!(coverage.for_file("lib.rs/other.rs").is_none());
+    }
+
+    #[test]
+    fn parse_cobertura() {
+        let coverage = Coverage::from_cobertura(COBERTURA).expect("cobertura parsed");
+
+        let lib = coverage.for_file("src/lib.rs").expect("lib coverage");
+        assert_eq!(lib.line_hits.len(), 2);
+        assert_eq!(lib.line_rate(), 0.5);
+        assert_eq!(lib.branch_rate(), Some(0.5));
+    }
+
+    #[test]
+    fn parse_cobertura_with_empty_methods() {
+        let report = r#"<coverage>
+  <class name="lib" filename="src/lib.rs">
+    <methods/>
+    <lines>
+      <line number="1" hits="1"/>
+      <line number="2" hits="0"/>
+    </lines>
+  </class>
+  <class name="main" filename="src/main.rs">
+    <methods />
+    <lines><line number="1" hits="3"/></lines>
+  </class>
+</coverage>"#;
+
+        let coverage = Coverage::from_cobertura(report).expect("cobertura parsed");
+
+        assert_eq!(
+            coverage.for_file("src/lib.rs").map(|f| f.line_rate()),
+            Some(0.5)
+        );
+        assert_eq!(
+            coverage.for_file("src/main.rs").map(|f| f.line_rate()),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn invalid_lcov_is_an_error() {
+        assert!(Coverage::from_lcov("DA:1,1\n").is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use eyre::{Context, Result};
+
+use crate::git::{count_changes, CommitRecord, RepositoryExplorer};
+use crate::metrics::{BlamedLine, Churn};
+
+/// Why a commit was left out of the history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Exclusion {
+    Merge,
+    Author,
+    Bulk,
+    IgnoredRevision,
+}
+
+impl Display for Exclusion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            Exclusion::Merge => "merges",
+            Exclusion::Author => "by excluded authors",
+            Exclusion::Bulk => "changing too many files",
+            Exclusion::IgnoredRevision => "listed as ignored revisions",
+        };
+        write!(f, "{reason}")
+    }
+}
+
+/// Commits to leave out of the history, such as bot updates or formatting sweeps
+#[derive(Debug, Clone, Default)]
+pub struct CommitFilter {
+    /// Merges list no file, so leaving them out changes the active authors, not the churn
+    pub skip_merges: bool,
+    /// Parts of the name or email of the excluded authors, ignoring case
+    pub authors: Vec<String>,
+    /// Commits changing more files than this are skipped
+    pub max_files: Option<usize>,
+    /// Ids of the skipped commits, possibly abbreviated
+    pub ignored_revisions: Vec<String>,
+}
+
+impl CommitFilter {
+    /// Add the revisions of a `.git-blame-ignore-revs` file, one per line with `#` comments
+    pub fn read_ignored_revisions(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path).wrap_err(format!(
+            "Unable to read ignored revisions {}",
+            path.display()
+        ))?;
+        self.ignored_revisions.extend(
+            content
+                .lines()
+                .map(|line| line.split('#').next().unwrap_or_default().trim())
+                .filter(|revision| !revision.is_empty())
+                .map(str::to_string),
+        );
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.skip_merges
+            && self.authors.is_empty()
+            && self.max_files.is_none()
+            && self.ignored_revisions.is_empty()
+    }
+
+    /// Reason to leave `commit` out, `None` to keep it
+    pub fn exclusion(&self, commit: &CommitRecord) -> Option<Exclusion> {
+        if self
+            .ignored_revisions
+            .iter()
+            .any(|revision| commit.id.starts_with(revision.as_str()))
+        {
+            return Some(Exclusion::IgnoredRevision);
+        }
+        if self.skip_merges && commit.is_merge {
+            return Some(Exclusion::Merge);
+        }
+        let author = format!("{} <{}>", commit.author, commit.email).to_lowercase();
+        if self
+            .authors
+            .iter()
+            .any(|excluded| author.contains(&excluded.to_lowercase()))
+        {
+            return Some(Exclusion::Author);
+        }
+        if self.max_files.is_some_and(|max| commit.files.len() > max) {
+            return Some(Exclusion::Bulk);
+        }
+        None
+    }
+}
+
+/// Commits left out by a [`Filtered`] explorer, shared with its owner once it is moved
+#[derive(Debug, Clone, Default)]
+pub struct ExclusionLog(Rc<RefCell<BTreeMap<String, Exclusion>>>);
+
+impl ExclusionLog {
+    /// Number of distinct commits left out for each reason
+    pub fn counts(&self) -> BTreeMap<Exclusion, usize> {
+        let mut counts = BTreeMap::new();
+        for exclusion in self.0.borrow().values() {
+            *counts.entry(*exclusion).or_default() += 1;
+        }
+        counts
+    }
+
+    /// One line summary of the commits left out, `None` when all were kept
+    pub fn summary(&self) -> Option<String> {
+        let counts = self.counts();
+        let total: usize = counts.values().sum();
+        if total == 0 {
+            return None;
+        }
+        let reasons: Vec<_> = counts
+            .iter()
+            .map(|(exclusion, count)| format!("{count} {exclusion}"))
+            .collect();
+        Some(format!("{total} commits excluded: {}", reasons.join(", ")))
+    }
+}
+
+/// Explorer leaving the commits rejected by a [`CommitFilter`] out of the churn
+pub struct Filtered<E> {
+    explorer: E,
+    filter: CommitFilter,
+    log: ExclusionLog,
+}
+
+impl<E: RepositoryExplorer> Filtered<E> {
+    pub fn new(explorer: E, filter: CommitFilter) -> Self {
+        Self {
+            explorer,
+            filter,
+            log: ExclusionLog::default(),
+        }
+    }
+
+    pub fn log(&self) -> ExclusionLog {
+        self.log.clone()
+    }
+
+    /// Whether `commit` passes the filter, logging why it does not
+    fn keep(&self, commit: &CommitRecord) -> bool {
+        match self.filter.exclusion(commit) {
+            Some(exclusion) => {
+                self.log.0.borrow_mut().insert(commit.id.clone(), exclusion);
+                false
+            }
+            None => true,
+        }
+    }
+}
+
+impl<E: RepositoryExplorer> RepositoryExplorer for Filtered<E> {
+    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+        self.change_count_per_file_until("HEAD")
+    }
+
+    fn change_count_per_file_until(&self, revision: &str) -> Result<HashMap<String, Churn>> {
+        Ok(count_changes(&self.commits(revision)?))
+    }
+
+    fn commits(&self, revision: &str) -> Result<Vec<CommitRecord>> {
+        let mut commits = self.explorer.commits(revision)?;
+        commits.retain(|commit| self.keep(commit));
+        Ok(commits)
+    }
+
+    /// Reachability is found on the whole history, before the commits are left out
+    fn commits_from(&self, revisions: &[String]) -> Result<Vec<(CommitRecord, Vec<usize>)>> {
+        let mut commits = self.explorer.commits_from(revisions)?;
+        commits.retain(|(commit, _)| self.keep(commit));
+        Ok(commits)
+    }
+
+    fn references(&self, pattern: &str) -> Result<Vec<String>> {
+        self.explorer.references(pattern)
+    }
+
+    fn merge_base(&self, base: &str, head: &str) -> Result<String> {
+        self.explorer.merge_base(base, head)
+    }
+
+    fn changed_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        self.explorer.changed_files(from, to)
+    }
+
+    fn file_content(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>> {
+        self.explorer.file_content(revision, path)
+    }
+
+    fn blame(&self, revision: &str, path: &str) -> Result<Vec<BlamedLine>> {
+        self.explorer.blame(revision, path)
+    }
+
+    fn worktree_changes(&self) -> Result<Vec<String>> {
+        self.explorer.worktree_changes()
+    }
+
+    fn shallow_depth(&self) -> Result<Option<usize>> {
+        self.explorer.shallow_depth()
+    }
+
+    fn submodules(&self) -> Result<Vec<(String, PathBuf)>> {
+        self.explorer.submodules()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use eyre::Result;
+
+    use crate::{git::CommitRecord, Churn, RepositoryExplorer};
+
+    use super::{CommitFilter, Exclusion, Filtered};
+
+    struct HistoryExplorer {}
+
+    impl RepositoryExplorer for HistoryExplorer {
+        fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+            Ok(HashMap::new())
+        }
+
+        fn commits(&self, _revision: &str) -> Result<Vec<CommitRecord>> {
+            Ok(vec![
+                CommitRecord::new("4444", "alice", &[]),
+                CommitRecord::new("3333", "dependabot[bot]", &["Cargo.toml"]),
+                CommitRecord::new("2222", "bob", &["a.rs", "b.rs", "c.rs"]),
+                CommitRecord::new("1111", "alice", &["a.rs"]),
+            ])
+        }
+    }
+
+    #[test]
+    fn skip_filtered_commits() {
+        let filter = CommitFilter {
+            skip_merges: true,
+            authors: vec!["Dependabot".to_string()],
+            max_files: Some(2),
+            ignored_revisions: vec![],
+        };
+        let explorer = Filtered::new(HistoryExplorer {}, filter);
+        let log = explorer.log();
+
+        let churn = explorer.change_count_per_file().expect("churn");
+
+        assert_eq!(churn, HashMap::from([("a.rs".to_string(), Churn::from(1))]));
+        assert_eq!(log.counts()[&Exclusion::Bulk], 1);
+        assert_eq!(
+            log.summary().expect("summary"),
+            "3 commits excluded: 1 merges, 1 by excluded authors, 1 changing too many files"
+        );
+    }
+
+    #[test]
+    fn ignored_revisions_file() {
+        let dir = tempfile::tempdir().expect("temp dir obtained");
+        let path = dir.path().join(".git-blame-ignore-revs");
+        std::fs::write(&path, "# cargo fmt\n2222\n\n1111 # typo\n").expect("file written");
+        let mut filter = CommitFilter::default();
+        filter
+            .read_ignored_revisions(&path)
+            .expect("revisions read");
+
+        assert_eq!(filter.ignored_revisions, vec!["2222", "1111"]);
+        let explorer = Filtered::new(HistoryExplorer {}, filter);
+        assert_eq!(explorer.commits("HEAD").expect("commits").len(), 2);
+        assert_eq!(explorer.log().counts()[&Exclusion::IgnoredRevision], 2);
+    }
+}
@@ -1,76 +1,655 @@
-use std::collections::HashMap;
+use std::cell::OnceCell;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
 use std::path::PathBuf;
 
-use eyre::{Result, WrapErr};
-use git_repository::{discover, Repository};
+use eyre::{eyre, Result, WrapErr};
+use git_repository::{discover, ObjectId, Repository};
 use git_repository::{objs::tree::EntryMode, traverse::tree::Recorder, Commit};
 
-use crate::metrics::Churn;
+use crate::metrics::{BlamedLine, Churn, LineOwner};
+use crate::pattern::wildcard_match;
+use crate::vcs::GitCli;
+
+/// A commit and the files it changed compared to its first parent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitRecord {
+    pub id: String,
+    pub message: String,
+    pub author: String,
+    pub email: String,
+    /// Author date, in seconds since the Unix epoch
+    pub time: i64,
+    /// Merges list no file, like in `git log`
+    pub is_merge: bool,
+    /// Ids of the parent commits, the first parent first
+    pub parents: Vec<String>,
+    pub files: Vec<String>,
+}
+
+#[cfg(test)]
+impl CommitRecord {
+    /// Commit `id` of `author` at the epoch, a merge when it changes no file
+    pub fn new(id: &str, author: &str, files: &[&str]) -> Self {
+        Self {
+            id: id.to_string(),
+            message: String::new(),
+            author: author.to_string(),
+            email: format!("{author}@example.com"),
+            time: 0,
+            is_merge: files.is_empty(),
+            parents: vec![],
+            files: files.iter().map(|file| file.to_string()).collect(),
+        }
+    }
+}
+
+/// Owners of the blamed lines, by decreasing number of lines
+pub fn count_owners(lines: &[BlamedLine]) -> Vec<LineOwner> {
+    let mut counts: HashMap<(String, String), usize> = HashMap::new();
+    for line in lines {
+        *counts
+            .entry((line.author.clone(), line.email.clone()))
+            .or_default() += 1;
+    }
+    let mut owners: Vec<_> = counts
+        .into_iter()
+        .map(|((author, email), lines)| LineOwner {
+            author,
+            email,
+            lines,
+        })
+        .collect();
+    owners.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.author.cmp(&b.author)));
+    owners
+}
+
+/// Number of commits changing each file
+pub fn count_changes<'a>(
+    commits: impl IntoIterator<Item = &'a CommitRecord>,
+) -> HashMap<String, Churn> {
+    let mut change_map: HashMap<String, Churn> = HashMap::new();
+    for file in commits.into_iter().flat_map(|commit| &commit.files) {
+        *change_map.entry(file.clone()).or_default() += 1;
+    }
+    change_map
+}
+
+/// Pair each commit with the positions in `tips` of the revisions it is reachable from, `tips`
+/// being the ids of the revisions whose history `commits` holds
+pub fn reachable_from(
+    tips: &[String],
+    commits: Vec<CommitRecord>,
+) -> Vec<(CommitRecord, Vec<usize>)> {
+    let positions: HashMap<&str, usize> = commits
+        .iter()
+        .enumerate()
+        .map(|(position, commit)| (commit.id.as_str(), position))
+        .collect();
+    let mut reached: Vec<Vec<usize>> = vec![vec![]; commits.len()];
+    for (tip_index, tip) in tips.iter().enumerate() {
+        let mut stack = vec![tip.as_str()];
+        while let Some(id) = stack.pop() {
+            let Some(&position) = positions.get(id) else {
+                continue;
+            };
+            if reached[position].last() == Some(&tip_index) {
+                continue;
+            }
+            reached[position].push(tip_index);
+            stack.extend(commits[position].parents.iter().map(String::as_str));
+        }
+    }
+    commits
+        .into_iter()
+        .zip(reached)
+        .filter(|(_, reached)| !reached.is_empty())
+        .collect()
+}
 
 pub trait RepositoryExplorer {
     fn change_count_per_file(&self) -> Result<HashMap<String, Churn>>;
+
+    /// Like [`Self::change_count_per_file`], counting only the history of `revision`
+    fn change_count_per_file_until(&self, revision: &str) -> Result<HashMap<String, Churn>> {
+        Ok(count_changes(&self.commits(revision)?))
+    }
+
+    /// Every commit reachable from `revision`, newest first
+    fn commits(&self, _revision: &str) -> Result<Vec<CommitRecord>> {
+        Err(eyre!("this repository explorer does not support revisions"))
+    }
+
+    /// Every commit reachable from any of `revisions`, once, newest first, with the positions in
+    /// `revisions` of those it is reachable from. Backends able to walk several revisions at once
+    /// read the history once.
+    fn commits_from(&self, revisions: &[String]) -> Result<Vec<(CommitRecord, Vec<usize>)>> {
+        let mut commits: Vec<(CommitRecord, Vec<usize>)> = vec![];
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for (index, revision) in revisions.iter().enumerate() {
+            for commit in self.commits(revision)? {
+                match positions.get(&commit.id) {
+                    Some(&position) => commits[position].1.push(index),
+                    None => {
+                        positions.insert(commit.id.clone(), commits.len());
+                        commits.push((commit, vec![index]));
+                    }
+                }
+            }
+        }
+        Ok(commits)
+    }
+
+    /// Full names of the references matching `pattern`, such as `refs/heads/release/*`
+    fn references(&self, _pattern: &str) -> Result<Vec<String>> {
+        Err(eyre!(
+            "this repository explorer does not support references"
+        ))
+    }
+
+    /// Id of the nearest common ancestor of both revisions, as `git merge-base` finds it
+    fn merge_base(&self, _base: &str, _head: &str) -> Result<String> {
+        Err(eyre!("this repository explorer does not support revisions"))
+    }
+
+    /// Files added, modified or removed between the two revisions, sorted by name
+    fn changed_files(&self, _from: &str, _to: &str) -> Result<Vec<String>> {
+        Err(eyre!("this repository explorer does not support revisions"))
+    }
+
+    /// Content of `path` at `revision`, `None` when the file does not exist there
+    fn file_content(&self, _revision: &str, _path: &str) -> Result<Option<Vec<u8>>> {
+        Err(eyre!("this repository explorer does not support revisions"))
+    }
+
+    /// Authors of the lines of `path` at `revision`, by decreasing number of lines
+    fn blame(&self, _revision: &str, _path: &str) -> Result<Vec<BlamedLine>> {
+        Err(eyre!("this repository explorer does not support blame"))
+    }
+
+    /// Files whose staged or unstaged content differs from `HEAD`, sorted by name
+    fn worktree_changes(&self) -> Result<Vec<String>> {
+        Err(eyre!(
+            "this repository explorer does not support working trees"
+        ))
+    }
+
+    /// Number of commits reachable from `HEAD` when the history is truncated, as in shallow clones
+    fn shallow_depth(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    /// Path of every submodule relative to the repository, with the directory it is checked out in
+    fn submodules(&self) -> Result<Vec<(String, PathBuf)>> {
+        Ok(vec![])
+    }
+}
+
+impl<T: RepositoryExplorer + ?Sized> RepositoryExplorer for Box<T> {
+    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+        (**self).change_count_per_file()
+    }
+
+    fn change_count_per_file_until(&self, revision: &str) -> Result<HashMap<String, Churn>> {
+        (**self).change_count_per_file_until(revision)
+    }
+
+    fn commits(&self, revision: &str) -> Result<Vec<CommitRecord>> {
+        (**self).commits(revision)
+    }
+
+    fn commits_from(&self, revisions: &[String]) -> Result<Vec<(CommitRecord, Vec<usize>)>> {
+        (**self).commits_from(revisions)
+    }
+
+    fn references(&self, pattern: &str) -> Result<Vec<String>> {
+        (**self).references(pattern)
+    }
+
+    fn merge_base(&self, base: &str, head: &str) -> Result<String> {
+        (**self).merge_base(base, head)
+    }
+
+    fn changed_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        (**self).changed_files(from, to)
+    }
+
+    fn file_content(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>> {
+        (**self).file_content(revision, path)
+    }
+
+    fn blame(&self, revision: &str, path: &str) -> Result<Vec<BlamedLine>> {
+        (**self).blame(revision, path)
+    }
+
+    fn worktree_changes(&self) -> Result<Vec<String>> {
+        (**self).worktree_changes()
+    }
+
+    fn shallow_depth(&self) -> Result<Option<usize>> {
+        (**self).shallow_depth()
+    }
+
+    fn submodules(&self) -> Result<Vec<(String, PathBuf)>> {
+        (**self).submodules()
+    }
+}
+
+impl<T: RepositoryExplorer + ?Sized> RepositoryExplorer for &T {
+    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+        (**self).change_count_per_file()
+    }
+
+    fn change_count_per_file_until(&self, revision: &str) -> Result<HashMap<String, Churn>> {
+        (**self).change_count_per_file_until(revision)
+    }
+
+    fn commits(&self, revision: &str) -> Result<Vec<CommitRecord>> {
+        (**self).commits(revision)
+    }
+
+    fn commits_from(&self, revisions: &[String]) -> Result<Vec<(CommitRecord, Vec<usize>)>> {
+        (**self).commits_from(revisions)
+    }
+
+    fn references(&self, pattern: &str) -> Result<Vec<String>> {
+        (**self).references(pattern)
+    }
+
+    fn merge_base(&self, base: &str, head: &str) -> Result<String> {
+        (**self).merge_base(base, head)
+    }
+
+    fn changed_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        (**self).changed_files(from, to)
+    }
+
+    fn file_content(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>> {
+        (**self).file_content(revision, path)
+    }
+
+    fn blame(&self, revision: &str, path: &str) -> Result<Vec<BlamedLine>> {
+        (**self).blame(revision, path)
+    }
+
+    fn worktree_changes(&self) -> Result<Vec<String>> {
+        (**self).worktree_changes()
+    }
+
+    fn shallow_depth(&self) -> Result<Option<usize>> {
+        (**self).shallow_depth()
+    }
+
+    fn submodules(&self) -> Result<Vec<(String, PathBuf)>> {
+        (**self).submodules()
+    }
+}
+
+/// Explorer counting the uncommitted changes of the working tree as one more commit
+pub struct WithWorktree<E> {
+    explorer: E,
+    changes: Vec<String>,
+}
+
+impl<E: RepositoryExplorer> WithWorktree<E> {
+    /// Read the uncommitted changes once, as listing them compares the whole working tree
+    pub fn new(explorer: E) -> Result<Self> {
+        let changes = explorer.worktree_changes()?;
+        Ok(Self { explorer, changes })
+    }
+
+    /// Files with uncommitted changes
+    pub fn changes(&self) -> &[String] {
+        &self.changes
+    }
+}
+
+impl<E: RepositoryExplorer> RepositoryExplorer for WithWorktree<E> {
+    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+        let mut change_map = self.explorer.change_count_per_file()?;
+        for file in &self.changes {
+            *change_map.entry(file.clone()).or_default() += 1;
+        }
+        Ok(change_map)
+    }
+
+    fn worktree_changes(&self) -> Result<Vec<String>> {
+        Ok(self.changes.clone())
+    }
+
+    fn change_count_per_file_until(&self, revision: &str) -> Result<HashMap<String, Churn>> {
+        self.explorer.change_count_per_file_until(revision)
+    }
+
+    fn commits(&self, revision: &str) -> Result<Vec<CommitRecord>> {
+        self.explorer.commits(revision)
+    }
+
+    fn commits_from(&self, revisions: &[String]) -> Result<Vec<(CommitRecord, Vec<usize>)>> {
+        self.explorer.commits_from(revisions)
+    }
+
+    fn references(&self, pattern: &str) -> Result<Vec<String>> {
+        self.explorer.references(pattern)
+    }
+
+    fn merge_base(&self, base: &str, head: &str) -> Result<String> {
+        self.explorer.merge_base(base, head)
+    }
+
+    fn changed_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        self.explorer.changed_files(from, to)
+    }
+
+    fn file_content(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>> {
+        self.explorer.file_content(revision, path)
+    }
+
+    fn blame(&self, revision: &str, path: &str) -> Result<Vec<BlamedLine>> {
+        self.explorer.blame(revision, path)
+    }
+
+    fn shallow_depth(&self) -> Result<Option<usize>> {
+        self.explorer.shallow_depth()
+    }
+
+    fn submodules(&self) -> Result<Vec<(String, PathBuf)>> {
+        self.explorer.submodules()
+    }
+}
+
+/// Error of gitoxide reading the history or objects of a repository it opened, which the git
+/// command line may still read, unlike a revision the repository does not hold
+#[derive(Debug)]
+pub struct ReadFailure(pub String);
+
+impl Display for ReadFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
 pub struct Gitoxide {
     repository: Repository,
+    /// Git command line doing what gitoxide cannot yet, started on first use
+    cli: OnceCell<GitCli>,
 }
 
 impl Gitoxide {
     pub fn try_new(path_to_repo: PathBuf) -> Result<Self> {
         let repository =
             discover(path_to_repo).wrap_err("Repository not found or without commits")?;
-        Ok(Self { repository })
+        Ok(Self {
+            repository,
+            cli: OnceCell::new(),
+        })
     }
-}
 
-impl RepositoryExplorer for Gitoxide {
-    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
-        let head = self
+    fn cli(&self) -> Result<&GitCli> {
+        if let Some(cli) = self.cli.get() {
+            return Ok(cli);
+        }
+        let work_dir = self
             .repository
-            .head_commit()
-            .wrap_err("Unable to get the head of the repo")?;
+            .work_dir()
+            .ok_or_else(|| eyre!("The repository has no working tree"))?;
+        let cli = GitCli::try_new(work_dir)?;
+        Ok(self.cli.get_or_init(|| cli))
+    }
+
+    fn commit(&self, revision: &str) -> Result<Commit<'_>> {
+        let id = self
+            .repository
+            .rev_parse_single(revision)
+            .wrap_err(format!("Unable to find revision {revision}"))?;
+        let object = id
+            .object()
+            .wrap_err(ReadFailure(format!("Unable to read revision {revision}")))?;
+        object
+            .peel_to_kind(git_repository::object::Kind::Commit)
+            .map(|object| object.into_commit())
+            .wrap_err(format!("Revision {revision} is not a commit"))
+    }
 
-        let commits = head
+    fn ancestors(&self, revision: &str) -> Result<Vec<ObjectId>> {
+        let commit = self.commit(revision)?;
+        let ancestors = commit
+            .id()
             .ancestors()
             .all()
-            .wrap_err("Unable to obtain commit ancestors of the current HEAD")?
+            .wrap_err(ReadFailure(format!(
+                "Unable to obtain commit ancestors of {revision}"
+            )))?
+            .flatten()
+            .map(|id| id.detach())
+            .collect();
+        Ok(ancestors)
+    }
+
+    /// Every commit reachable from any of `tips`, described as `revisions` in errors
+    fn walk(&self, tips: Vec<ObjectId>, revisions: &str) -> Result<Vec<CommitRecord>> {
+        let commits = self
+            .repository
+            .rev_walk(tips)
+            .all()
+            .wrap_err(ReadFailure(format!(
+                "Unable to obtain commit ancestors of {revisions}"
+            )))?
             .flatten()
-            .flat_map(|reference| reference.object())
+            .flat_map(|id| id.object())
             .flat_map(Commit::try_from);
 
-        let mut change_map = HashMap::new();
+        let mut records = vec![];
+        // The walk usually reaches a parent right after its child, so its blobs are kept
+        let mut previous: Option<(ObjectId, HashMap<String, ObjectId>)> = None;
 
         for commit in commits {
-            let change_tree = commit.tree().wrap_err(format!(
-                "Unable to obtain the change tree of commit {}",
+            let parents: Vec<_> = commit.parent_ids().map(|id| id.detach()).collect();
+            let author = commit.author().wrap_err(ReadFailure(format!(
+                "Unable to read the author of commit {}",
                 commit.id
-            ))?;
-
-            let changes = change_tree.traverse();
-
-            let mut recorder = Recorder::default();
-            let _ = changes.breadthfirst(&mut recorder);
-
-            for entry in recorder.records {
-                if let &EntryMode::Blob = &entry.mode {
-                    match change_map.entry(entry.filepath.to_string()) {
-                        std::collections::hash_map::Entry::Occupied(mut e) => {
-                            *e.get_mut() += 1;
-                        }
-                        std::collections::hash_map::Entry::Vacant(e) => {
-                            e.insert(1);
-                        }
-                    }
-                }
+            )))?;
+            let mut record = CommitRecord {
+                id: commit.id.to_string(),
+                message: commit
+                    .message_raw_sloppy()
+                    .to_string()
+                    .trim_end()
+                    .to_string(),
+                author: author.name.to_string(),
+                email: author.email.to_string(),
+                time: author.time.seconds_since_unix_epoch.into(),
+                is_merge: parents.len() > 1,
+                parents: parents.iter().map(ObjectId::to_string).collect(),
+                files: vec![],
+            };
+            // Like `git log`, merges do not list the files they change
+            if record.is_merge {
+                previous = None;
+                records.push(record);
+                continue;
             }
+
+            let blobs = match previous.take() {
+                Some((id, blobs)) if id == commit.id => blobs,
+                _ => Self::commit_blobs(&commit)?,
+            };
+            let parent_blobs = match parents.first() {
+                Some(parent) => {
+                    let parent = self
+                        .repository
+                        .find_object(*parent)
+                        .wrap_err(ReadFailure(format!(
+                            "Unable to read the parent of commit {}",
+                            commit.id
+                        )))?
+                        .try_into_commit()
+                        .wrap_err(ReadFailure(format!(
+                            "Parent of commit {} is not a commit",
+                            commit.id
+                        )))?;
+                    Self::commit_blobs(&parent)?
+                }
+                None => HashMap::new(),
+            };
+
+            record.files = blobs
+                .iter()
+                .filter(|(path, id)| parent_blobs.get(*path) != Some(id))
+                .map(|(path, _)| path.clone())
+                .collect();
+            record.files.sort();
+            records.push(record);
+
+            previous = parents.first().map(|parent| (*parent, parent_blobs));
         }
 
-        let map = change_map
+        Ok(records)
+    }
+
+    /// Id of every file blob of the tree of `revision`, by path
+    fn blobs(&self, revision: &str) -> Result<HashMap<String, ObjectId>> {
+        let commit = self.commit(revision)?;
+        Self::commit_blobs(&commit)
+    }
+
+    fn commit_blobs(commit: &Commit<'_>) -> Result<HashMap<String, ObjectId>> {
+        let tree = commit.tree().wrap_err(ReadFailure(format!(
+            "Unable to obtain the change tree of commit {}",
+            commit.id
+        )))?;
+
+        let mut recorder = Recorder::default();
+        tree.traverse()
+            .breadthfirst(&mut recorder)
+            .wrap_err(ReadFailure(format!(
+                "Unable to walk the tree of commit {}",
+                commit.id
+            )))?;
+
+        Ok(recorder
+            .records
             .into_iter()
-            .map(|(file, churn)| (file, Churn::from(churn)))
+            .filter(|entry| entry.mode == EntryMode::Blob)
+            .map(|entry| (entry.filepath.to_string(), entry.oid))
+            .collect())
+    }
+}
+
+impl RepositoryExplorer for Gitoxide {
+    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+        self.change_count_per_file_until("HEAD")
+    }
+
+    fn commits(&self, revision: &str) -> Result<Vec<CommitRecord>> {
+        let head = self.commit(revision)?;
+        self.walk(vec![head.id], revision)
+    }
+
+    fn commits_from(&self, revisions: &[String]) -> Result<Vec<(CommitRecord, Vec<usize>)>> {
+        let tips = revisions
+            .iter()
+            .map(|revision| Ok(self.commit(revision)?.id))
+            .collect::<Result<Vec<_>>>()?;
+        let commits = self.walk(tips.clone(), &revisions.join(", "))?;
+        let tips: Vec<_> = tips.iter().map(ObjectId::to_string).collect();
+        Ok(reachable_from(&tips, commits))
+    }
+    fn references(&self, pattern: &str) -> Result<Vec<String>> {
+        let references = self
+            .repository
+            .references()
+            .wrap_err(ReadFailure("Unable to read the references".to_string()))?;
+        let mut names: Vec<_> = references
+            .all()
+            .wrap_err(ReadFailure("Unable to read the references".to_string()))?
+            .flatten()
+            .map(|reference| reference.name().as_bstr().to_string())
+            .filter(|name| wildcard_match(pattern, name))
             .collect();
+        names.sort();
+        Ok(names)
+    }
 
-        Ok(map)
+    /// Gitoxide has no merge base yet, and the first common ancestor of a walk is not the best
+    /// one in criss-cross histories, so the git command line finds it
+    fn merge_base(&self, base: &str, head: &str) -> Result<String> {
+        self.cli()?.merge_base(base, head)
+    }
+
+    fn changed_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        let before = self.blobs(from)?;
+        let after = self.blobs(to)?;
+
+        let changed: BTreeSet<_> = before
+            .iter()
+            .filter(|(path, id)| after.get(*path) != Some(id))
+            .chain(after.iter().filter(|(path, _)| !before.contains_key(*path)))
+            .map(|(path, _)| path.clone())
+            .collect();
+        Ok(changed.into_iter().collect())
+    }
+
+    fn file_content(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>> {
+        let tree = self.commit(revision)?.tree().wrap_err(ReadFailure(format!(
+            "Unable to obtain the tree of {revision}"
+        )))?;
+        let entry = tree
+            .lookup_entry_by_path(path)
+            .wrap_err(ReadFailure(format!(
+                "Unable to look {path} up at {revision}"
+            )))?;
+        let Some(entry) = entry.filter(|entry| entry.mode() == EntryMode::Blob) else {
+            return Ok(None);
+        };
+        let blob = entry
+            .object()
+            .wrap_err(ReadFailure(format!("Unable to read {path} at {revision}")))?;
+        Ok(Some(blob.detach().data))
+    }
+
+    /// Gitoxide cannot blame yet, so the git command line does it
+    fn blame(&self, revision: &str, path: &str) -> Result<Vec<BlamedLine>> {
+        self.cli()?.blame(revision, path)
+    }
+
+    /// The git command line compares the working tree through the stat cache of the index and
+    /// applies the configured filters, such as `core.autocrlf`
+    fn worktree_changes(&self) -> Result<Vec<String>> {
+        self.cli()?.worktree_changes()
+    }
+
+    fn shallow_depth(&self) -> Result<Option<usize>> {
+        if !self.repository.common_dir().join("shallow").exists() {
+            return Ok(None);
+        }
+        Ok(Some(self.ancestors("HEAD")?.len()))
+    }
+
+    fn submodules(&self) -> Result<Vec<(String, PathBuf)>> {
+        let Some(work_dir) = self.repository.work_dir() else {
+            return Ok(vec![]);
+        };
+        let tree = self
+            .commit("HEAD")?
+            .tree()
+            .wrap_err(ReadFailure("Unable to obtain the tree of HEAD".to_string()))?;
+
+        let mut recorder = Recorder::default();
+        tree.traverse()
+            .breadthfirst(&mut recorder)
+            .wrap_err(ReadFailure("Unable to walk the tree of HEAD".to_string()))?;
+
+        Ok(recorder
+            .records
+            .into_iter()
+            .filter(|entry| entry.mode == EntryMode::Commit)
+            .map(|entry| {
+                let path = entry.filepath.to_string();
+                let directory = work_dir.join(&path);
+                (path, directory)
+            })
+            .collect())
     }
 }
 
@@ -90,4 +669,25 @@ mod integration {
             .expect("list of change count");
         assert!(churn_metrics.contains_key("src/main.rs"));
     }
+
+    #[test]
+    fn gitoxide_reads_revisions() {
+        let path = env::current_dir().expect("current dir path");
+        let explorer = Gitoxide::try_new(path).expect("gitoxide init");
+
+        let head = explorer.merge_base("HEAD", "HEAD").expect("merge base");
+        assert_eq!(head.len(), 40);
+        assert!(explorer
+            .changed_files("HEAD", "HEAD")
+            .expect("changed files")
+            .is_empty());
+        assert!(explorer
+            .file_content(&head, "Cargo.toml")
+            .expect("file content")
+            .is_some());
+        assert!(explorer
+            .file_content("HEAD", "missing.rs")
+            .expect("file content")
+            .is_none());
+    }
 }
@@ -0,0 +1,471 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use eyre::{eyre, Context, Result};
+use regex::Regex;
+
+use crate::git::{count_changes, CommitRecord, RepositoryExplorer};
+use crate::metrics::{Churn, CommitSummary, LineOwner};
+
+/// Churn of the commits reachable from several references
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RefChurn {
+    /// Churn of every reachable commit, each counted once even when reachable from several references
+    pub total: HashMap<String, Churn>,
+    /// Churn of the commits reachable from each reference, by short reference name
+    pub per_ref: BTreeMap<String, HashMap<String, Churn>>,
+    /// Every reachable commit, once, newest first
+    pub commits: Vec<CommitRecord>,
+}
+
+/// Churn of the commits reachable from the references matching any of `patterns`
+pub fn ref_churn(explorer: &impl RepositoryExplorer, patterns: &[String]) -> Result<RefChurn> {
+    let mut names = vec![];
+    for pattern in patterns {
+        let matching = explorer
+            .references(pattern)
+            .wrap_err(format!("Unable to list the references matching {pattern}"))?;
+        if matching.is_empty() {
+            return Err(eyre!("no reference matches {pattern}"));
+        }
+        names.extend(matching);
+    }
+    names.sort();
+    names.dedup();
+
+    let commits = explorer.commits_from(&names)?;
+    let mut per_ref = BTreeMap::new();
+    for (index, name) in names.iter().enumerate() {
+        let reachable = commits
+            .iter()
+            .filter(|(_, reached)| reached.contains(&index))
+            .map(|(commit, _)| commit);
+        per_ref.insert(short_ref_name(name).to_string(), count_changes(reachable));
+    }
+    let mut commits: Vec<CommitRecord> = commits.into_iter().map(|(commit, _)| commit).collect();
+    // Each reference is walked newest first, but the walks follow one another
+    commits.sort_by_key(|commit| Reverse(commit.time));
+
+    Ok(RefChurn {
+        total: count_changes(&commits),
+        per_ref,
+        commits,
+    })
+}
+
+/// Dates of the oldest and newest commits changing a file, in seconds since the Unix epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileDates {
+    pub first: i64,
+    pub last: i64,
+}
+
+/// Dates of the first and last commits changing each file
+pub fn file_dates<'a>(
+    commits: impl IntoIterator<Item = &'a CommitRecord>,
+) -> HashMap<String, FileDates> {
+    let mut dates: HashMap<String, FileDates> = HashMap::new();
+    for commit in commits {
+        for file in &commit.files {
+            dates
+                .entry(file.clone())
+                .and_modify(|dates| {
+                    dates.first = dates.first.min(commit.time);
+                    dates.last = dates.last.max(commit.time);
+                })
+                .or_insert(FileDates {
+                    first: commit.time,
+                    last: commit.time,
+                });
+        }
+    }
+    dates
+}
+
+/// Latest commits changing each file, at most `count` per file, from commits given newest first
+pub fn recent_commits<'a>(
+    commits: impl IntoIterator<Item = &'a CommitRecord>,
+    count: usize,
+) -> HashMap<String, Vec<CommitSummary>> {
+    let mut recent: HashMap<String, Vec<CommitSummary>> = HashMap::new();
+    for commit in commits {
+        for file in &commit.files {
+            let summaries = recent.entry(file.clone()).or_default();
+            if summaries.len() < count {
+                summaries.push(CommitSummary {
+                    id: commit.id.clone(),
+                    time: commit.time,
+                    author: commit.author.clone(),
+                    summary: commit
+                        .message
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                });
+            }
+        }
+    }
+    recent
+}
+
+/// Commits changing each file in each of the `months` months up to `newest`, oldest month first
+pub fn monthly_churn<'a>(
+    commits: impl IntoIterator<Item = &'a CommitRecord>,
+    newest: i64,
+    months: usize,
+) -> HashMap<String, Vec<u64>> {
+    let mut churn: HashMap<String, Vec<u64>> = HashMap::new();
+    for commit in commits {
+        let months_ago = (newest - commit.time).max(0) / SECONDS_PER_MONTH;
+        let Some(index) = usize::try_from(months_ago)
+            .ok()
+            .filter(|months_ago| *months_ago < months)
+            .map(|months_ago| months - 1 - months_ago)
+        else {
+            continue;
+        };
+        for file in &commit.files {
+            churn.entry(file.clone()).or_insert_with(|| vec![0; months])[index] += 1;
+        }
+    }
+    churn
+}
+
+/// Length of a month when grouping commits, in seconds
+pub const SECONDS_PER_MONTH: i64 = 30 * 24 * 60 * 60;
+
+/// Authors still working on the project, known by name or email regardless of case
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActiveAuthors(HashSet<String>);
+
+impl ActiveAuthors {
+    /// Read one name or email per line, ignoring blank lines and `#` comments
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .wrap_err(format!("Unable to read active authors {}", path.display()))?;
+        Ok(Self(
+            content
+                .lines()
+                .map(|line| line.split('#').next().unwrap_or_default().trim())
+                .filter(|author| !author.is_empty())
+                .map(str::to_lowercase)
+                .collect(),
+        ))
+    }
+
+    /// Authors of the commits made at `since` or later, in seconds since the Unix epoch
+    pub fn since<'a>(commits: impl IntoIterator<Item = &'a CommitRecord>, since: i64) -> Self {
+        Self(
+            commits
+                .into_iter()
+                .filter(|commit| commit.time >= since)
+                .flat_map(|commit| [commit.author.to_lowercase(), commit.email.to_lowercase()])
+                .collect(),
+        )
+    }
+
+    pub fn contains(&self, commit: &CommitRecord) -> bool {
+        self.is_active(&commit.author, &commit.email)
+    }
+
+    pub fn is_active(&self, author: &str, email: &str) -> bool {
+        self.0.contains(&author.to_lowercase()) || self.0.contains(&email.to_lowercase())
+    }
+}
+
+/// Share of the blamed lines last changed by authors who are no longer active, `None` without lines
+pub fn departed_line_share(owners: &[LineOwner], active: &ActiveAuthors) -> Option<f64> {
+    let total: usize = owners.iter().map(|owner| owner.lines).sum();
+    if total == 0 {
+        return None;
+    }
+    let departed: usize = owners
+        .iter()
+        .filter(|owner| !active.is_active(&owner.author, &owner.email))
+        .map(|owner| owner.lines)
+        .sum();
+    Some(departed as f64 / total as f64)
+}
+
+/// Share of the commits changing each file made by authors who are no longer active
+pub fn departed_shares<'a>(
+    commits: impl IntoIterator<Item = &'a CommitRecord>,
+    active: &ActiveAuthors,
+) -> HashMap<String, f64> {
+    let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+    for commit in commits {
+        let departed = !active.contains(commit);
+        for file in &commit.files {
+            let (departed_count, total) = counts.entry(file).or_default();
+            *departed_count += usize::from(departed);
+            *total += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .map(|(file, (departed, total))| (file.to_string(), departed as f64 / total as f64))
+        .collect()
+}
+
+/// Intent of a commit, read from its message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitKind {
+    Fix,
+    Other,
+}
+
+/// Classify commits with conventional commit prefixes, as used by git-cliff, and extra
+/// patterns marking fixes such as issue keys
+#[derive(Debug, Clone)]
+pub struct Classifier {
+    fix_patterns: Vec<Regex>,
+}
+
+impl Classifier {
+    /// Recognise `fix:` prefixes and their variants, with an optional scope, plus the messages
+    /// matching any of `fix_patterns`
+    pub fn new(fix_patterns: &[String]) -> Result<Self> {
+        let mut fixes = vec![r"(?i)^(fix|bugfix|hotfix)(\([^)]*\))?!?:".to_string()];
+        fixes.extend(fix_patterns.iter().cloned());
+        let fix_patterns = fixes
+            .iter()
+            .map(|pattern| Regex::new(pattern).wrap_err(format!("Invalid fix pattern {pattern}")))
+            .collect::<Result<_>>()?;
+        Ok(Self { fix_patterns })
+    }
+
+    pub fn classify(&self, message: &str) -> CommitKind {
+        let message = message.trim_start();
+        if self
+            .fix_patterns
+            .iter()
+            .any(|regex| regex.is_match(message))
+        {
+            CommitKind::Fix
+        } else {
+            CommitKind::Other
+        }
+    }
+}
+
+/// Number of fix commits changing each file
+pub fn bugfix_counts<'a>(
+    commits: impl IntoIterator<Item = &'a CommitRecord>,
+    classifier: &Classifier,
+) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for commit in commits {
+        if classifier.classify(&commit.message) == CommitKind::Fix {
+            for file in &commit.files {
+                *counts.entry(file.clone()).or_default() += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Reference name without its `refs/heads/`, `refs/remotes/` or `refs/tags/` prefix
+pub fn short_ref_name(name: &str) -> &str {
+    ["refs/heads/", "refs/remotes/", "refs/tags/"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use eyre::Result;
+
+    use crate::{git::CommitRecord, metrics::LineOwner, Churn, RepositoryExplorer};
+
+    use super::{
+        bugfix_counts, departed_line_share, departed_shares, file_dates, monthly_churn,
+        recent_commits, ref_churn, short_ref_name, ActiveAuthors, Classifier, CommitKind,
+        FileDates, SECONDS_PER_MONTH,
+    };
+
+    /// `main` and `release` share their first commit
+    struct BranchesExplorer {}
+
+    impl RepositoryExplorer for BranchesExplorer {
+        fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+            Ok(HashMap::new())
+        }
+
+        fn commits(&self, revision: &str) -> Result<Vec<CommitRecord>> {
+            Ok(match revision {
+                "refs/heads/main" => vec![
+                    CommitRecord::new("2", "Test", &["a.rs"]),
+                    CommitRecord::new("1", "Test", &["a.rs"]),
+                ],
+                _ => vec![
+                    CommitRecord::new("3", "Test", &["a.rs", "b.rs"]),
+                    CommitRecord::new("1", "Test", &["a.rs"]),
+                ],
+            })
+        }
+
+        fn references(&self, pattern: &str) -> Result<Vec<String>> {
+            Ok(match pattern {
+                "refs/heads/*" => vec![
+                    "refs/heads/main".to_string(),
+                    "refs/heads/release/1.0".to_string(),
+                ],
+                _ => vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn shared_commits_count_once() {
+        let churn =
+            ref_churn(&BranchesExplorer {}, &["refs/heads/*".to_string()]).expect("ref churn");
+
+        assert_eq!(churn.total["a.rs"], Churn::from(3));
+        assert_eq!(churn.total["b.rs"], Churn::from(1));
+        assert_eq!(churn.per_ref["main"]["a.rs"], Churn::from(2));
+        assert_eq!(churn.per_ref["release/1.0"]["b.rs"], Churn::from(1));
+        assert_eq!(churn.commits.len(), 3);
+    }
+
+    #[test]
+    fn unknown_pattern_is_an_error() {
+        assert!(ref_churn(&BranchesExplorer {}, &["refs/tags/*".to_string()]).is_err());
+    }
+
+    #[test]
+    fn classify_messages() {
+        let classifier = Classifier::new(&[r"^BUG-\d+".to_string()]).expect("classifier");
+
+        assert_eq!(classifier.classify("fix: off by one"), CommitKind::Fix);
+        assert_eq!(classifier.classify("fix(git)!: merges"), CommitKind::Fix);
+        assert_eq!(
+            classifier.classify("BUG-12 crash on start"),
+            CommitKind::Fix
+        );
+        assert_eq!(classifier.classify("feat(tui): sort"), CommitKind::Other);
+        assert_eq!(classifier.classify("prefix: not a fix"), CommitKind::Other);
+        assert!(Classifier::new(&["(".to_string()]).is_err());
+    }
+
+    #[test]
+    fn count_fixes_per_file() {
+        let mut fix = CommitRecord::new("2", "Test", &["a.rs"]);
+        fix.message = "fix: a".to_string();
+        let commits = [fix, CommitRecord::new("1", "Test", &["a.rs", "b.rs"])];
+
+        let counts = bugfix_counts(&commits, &Classifier::new(&[]).expect("classifier"));
+
+        assert_eq!(counts["a.rs"], 1);
+        assert!(!counts.contains_key("b.rs"));
+    }
+
+    #[test]
+    fn first_and_last_dates() {
+        let mut commits = [
+            CommitRecord::new("3", "Test", &["a.rs"]),
+            CommitRecord::new("2", "Test", &["a.rs", "b.rs"]),
+            CommitRecord::new("1", "Test", &["a.rs"]),
+        ];
+        for (commit, time) in commits.iter_mut().zip([300, 200, 100]) {
+            commit.time = time;
+        }
+
+        let dates = file_dates(&commits);
+
+        assert_eq!(
+            dates["a.rs"],
+            FileDates {
+                first: 100,
+                last: 300
+            }
+        );
+        assert_eq!(
+            dates["b.rs"],
+            FileDates {
+                first: 200,
+                last: 200
+            }
+        );
+    }
+
+    #[test]
+    fn activity_per_file() {
+        let mut commits = [
+            CommitRecord::new("3", "Test", &["a.rs"]),
+            CommitRecord::new("2", "Test", &["a.rs", "b.rs"]),
+            CommitRecord::new("1", "Test", &["a.rs"]),
+        ];
+        commits[0].time = 3 * SECONDS_PER_MONTH;
+        commits[0].message = "fix: a\n\nDetails".to_string();
+        commits[1].time = 2 * SECONDS_PER_MONTH;
+        commits[2].time = 0;
+
+        let recent = recent_commits(&commits, 2);
+        let churn = monthly_churn(&commits, 3 * SECONDS_PER_MONTH, 3);
+
+        let ids: Vec<_> = recent["a.rs"].iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["3", "2"]);
+        assert_eq!(recent["a.rs"][0].summary, "fix: a");
+        assert_eq!(churn["a.rs"], vec![0, 1, 1]);
+        assert_eq!(churn["b.rs"], vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn changes_by_departed_authors() {
+        let mut commits = [
+            CommitRecord::new("3", "Test", &["a.rs"]),
+            CommitRecord::new("2", "Test", &["a.rs", "b.rs"]),
+            CommitRecord::new("1", "Test", &["a.rs"]),
+        ];
+        commits[0].time = 300;
+        commits[1].author = "Gone".to_string();
+        commits[1].email = "gone@example.com".to_string();
+        commits[2].author = "Gone".to_string();
+        commits[2].email = "gone@example.com".to_string();
+
+        let active = ActiveAuthors::since(&commits, 200);
+        let shares = departed_shares(&commits, &active);
+
+        assert!(active.contains(&commits[0]));
+        assert!((shares["a.rs"] - 2.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(shares["b.rs"], 1.0);
+    }
+
+    #[test]
+    fn lines_by_departed_authors() {
+        let active = ActiveAuthors::since(&[CommitRecord::new("1", "Test", &["a.rs"])], 0);
+        let share = departed_line_share(
+            &[LineOwner::new("Test", 1), LineOwner::new("Gone", 3)],
+            &active,
+        );
+
+        assert_eq!(share, Some(0.75));
+        assert_eq!(departed_line_share(&[], &active), None);
+    }
+
+    #[test]
+    fn read_active_authors() {
+        let dir = tempfile::tempdir().expect("temp dir obtained");
+        let path = dir.path().join("authors");
+        std::fs::write(&path, "# team\nTEST@example.com\n\n").expect("file written");
+
+        let active = ActiveAuthors::read(&path).expect("authors read");
+
+        assert!(active.contains(&CommitRecord::new("1", "Test", &[])));
+    }
+
+    #[test]
+    fn short_names() {
+        assert_eq!(short_ref_name("refs/heads/release/1.0"), "release/1.0");
+        assert_eq!(short_ref_name("refs/remotes/origin/main"), "origin/main");
+        assert_eq!(short_ref_name("HEAD"), "HEAD");
+    }
+}
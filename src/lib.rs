@@ -1,12 +1,37 @@
-use crate::metrics::metrics_per_file;
-use eyre::{Context, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 
+use crate::filter::{CommitFilter, ExclusionLog, Filtered};
+use crate::git::{count_changes, CommitRecord, WithWorktree};
+use crate::history::{
+    bugfix_counts, departed_line_share, departed_shares, file_dates, monthly_churn, recent_commits,
+    ref_churn, ActiveAuthors, Classifier, RefChurn, SECONDS_PER_MONTH,
+};
+use crate::metrics::{metrics_per_file, FileMetrics};
+use crate::vcs::{open_explorer, Vcs, WithSubmodules};
+use eyre::{eyre, Context, Result};
+
+pub mod coverage;
+pub mod filter;
 pub mod git;
+pub mod history;
 pub mod metrics;
 pub mod output;
+mod pattern;
+pub mod pr;
+pub mod thresholds;
+pub mod vcs;
+pub mod workspace;
 
 pub use crate::git::RepositoryExplorer;
 pub use crate::metrics::{Churn, MetricReader, ProjectMetrics};
+pub use crate::workspace::Workspace;
+
+/// Number of latest commits kept for the details of each file
+const RECENT_COMMIT_COUNT: usize = 5;
+
+/// Number of months of churn drawn in the details of each file
+const ACTIVITY_MONTHS: usize = 12;
 
 pub fn get_metrics(
     git_explorer: impl RepositoryExplorer,
@@ -15,10 +40,422 @@ pub fn get_metrics(
     let change_map = git_explorer
         .change_count_per_file()
         .wrap_err("Unable to obtain the change count per file")?;
+    Ok(metrics_from_churn(change_map, reader))
+}
+
+/// Like [`get_metrics`], with the churn already counted, such as from a history read once
+pub fn metrics_from_churn(
+    change_map: HashMap<String, Churn>,
+    reader: impl MetricReader,
+) -> ProjectMetrics {
     let mut results = metrics_per_file(change_map, reader);
 
     results.sort_by(|a, b| a.magnitude().partial_cmp(&b.magnitude()).unwrap());
     results.reverse();
 
+    ProjectMetrics::new(results)
+}
+
+/// Like [`get_metrics`], with the churn and complexity of `revision` instead of the working tree
+pub fn get_metrics_at(
+    git_explorer: &impl RepositoryExplorer,
+    reader: &impl MetricReader,
+    revision: &str,
+) -> Result<ProjectMetrics> {
+    let change_map = git_explorer
+        .change_count_per_file_until(revision)
+        .wrap_err(format!(
+            "Unable to obtain the change count per file at {revision}"
+        ))?;
+    metrics_from_churn_at(git_explorer, reader, revision, change_map)
+}
+
+/// Like [`get_metrics_at`], with the churn until `revision` already counted
+pub fn metrics_from_churn_at(
+    git_explorer: &impl RepositoryExplorer,
+    reader: &impl MetricReader,
+    revision: &str,
+    change_map: HashMap<String, Churn>,
+) -> Result<ProjectMetrics> {
+    let mut results = vec![];
+    for (filename, churn) in change_map {
+        let Some(content) = git_explorer.file_content(revision, &filename)? else {
+            continue;
+        };
+        if let Some(complexity) = reader.get_cyclomatic_from_content(Path::new(&filename), content)
+        {
+            results.push(FileMetrics::new(filename, churn, complexity));
+        }
+    }
+
+    results.sort_by(|a, b| b.magnitude().total_cmp(&a.magnitude()));
+
     Ok(ProjectMetrics::new(results))
 }
+
+/// Like [`get_metrics`], with the churn of the commits reachable from the references matching
+/// `patterns`, in total and per reference.
+pub fn get_metrics_for_refs(
+    git_explorer: &impl RepositoryExplorer,
+    reader: impl MetricReader,
+    patterns: &[String],
+) -> Result<ProjectMetrics> {
+    let churn = ref_churn(git_explorer, patterns)?;
+    Ok(metrics_from_ref_churn(&churn, reader))
+}
+
+/// Like [`get_metrics_for_refs`], with the commits of the references already read
+pub fn metrics_from_ref_churn(churn: &RefChurn, reader: impl MetricReader) -> ProjectMetrics {
+    let mut results = metrics_per_file(churn.total.clone(), reader);
+
+    for metric in results.iter_mut() {
+        for (name, changes) in &churn.per_ref {
+            if let Some(count) = changes.get(&metric.filename) {
+                metric.churn_per_ref.insert(name.clone(), *count);
+            }
+        }
+    }
+    results.sort_by(|a, b| b.magnitude().total_cmp(&a.magnitude()));
+
+    ProjectMetrics::new(results)
+}
+
+/// How to open a repository and which of its commits to keep
+#[derive(Debug, Clone, Default)]
+pub struct RepositoryOptions {
+    pub vcs: Vcs,
+    /// Fail instead of warning when the history is truncated, as in shallow clones
+    pub require_full_history: bool,
+    /// Add the history of the checked out submodules
+    pub submodules: bool,
+    /// Commits left out of the history
+    pub filter: CommitFilter,
+}
+
+/// Open the repository holding `path` as `options` ask, with the log of the commits left out
+/// when some are filtered
+pub fn open_repository(
+    path: PathBuf,
+    options: &RepositoryOptions,
+) -> Result<(Box<dyn RepositoryExplorer>, Option<ExclusionLog>)> {
+    let explorer = open_explorer(path, options.vcs).wrap_err("Unable to initialise repository")?;
+    check_history(explorer.as_ref(), options.require_full_history)?;
+    let explorer: Box<dyn RepositoryExplorer> = if options.submodules {
+        let explorer = WithSubmodules::open(explorer, options.vcs)?;
+        for skipped in explorer.skipped() {
+            eprintln!("warning: submodule {skipped} is not checked out and left out");
+        }
+        Box::new(explorer)
+    } else {
+        explorer
+    };
+    // Filtered last, so that the commits of the submodules are left out alike
+    if options.filter.is_empty() {
+        return Ok((explorer, None));
+    }
+    let explorer = Filtered::new(explorer, options.filter.clone());
+    let log = explorer.log();
+    Ok((Box::new(explorer), Some(log)))
+}
+
+/// Warn about truncated histories, or fail when the full history is required
+fn check_history(explorer: &dyn RepositoryExplorer, require_full_history: bool) -> Result<()> {
+    let Some(depth) = explorer.shallow_depth()? else {
+        return Ok(());
+    };
+    let message = format!(
+        "the repository is a shallow clone, churn only covers the last {depth} commits \
+         (run `git fetch --unshallow` to get the full history)"
+    );
+    if require_full_history {
+        return Err(eyre!(message));
+    }
+    eprintln!("warning: {message}");
+    Ok(())
+}
+
+/// What to analyse and which metrics to add to the churn and complexity of the files
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisOptions {
+    /// Root of the project, holding the workspace manifest of the working tree
+    pub project_path: PathBuf,
+    /// Revision whose history and files are read instead of the working tree
+    pub revision: Option<String>,
+    /// Patterns of the references whose commits are counted instead of the ones of `HEAD`
+    pub ref_patterns: Vec<String>,
+    /// Count uncommitted changes as one more commit and flag the files holding them
+    pub include_worktree: bool,
+    /// Read the commits themselves, which exported logs lack, for the metrics drawn from them
+    pub commits: bool,
+    /// Read the functions of every file
+    pub functions: bool,
+    /// Count the fix commits of each file, recognised by this classifier
+    pub bugfixes: Option<Classifier>,
+    /// Blame the files to tell who owns their lines
+    pub blame: bool,
+    /// Authors still active, for the share of each file changed by departed ones
+    pub active_authors: Option<ActiveAuthors>,
+    /// Consider active the authors who committed in the last months of the history instead
+    pub active_months: Option<i64>,
+    /// Workspace packages to restrict the files to
+    pub packages: Vec<String>,
+}
+
+/// Collect the metrics of the project read by `explorer`, restricted and enriched as `options` ask
+pub fn analyse(
+    explorer: &impl RepositoryExplorer,
+    reader: &impl MetricReader,
+    options: &AnalysisOptions,
+) -> Result<ProjectMetrics> {
+    let revision = options.revision.as_deref();
+    // The commits of the references replace the ones of `HEAD`, all read in one walk
+    let refs = if options.ref_patterns.is_empty() {
+        None
+    } else {
+        Some(ref_churn(explorer, &options.ref_patterns)?)
+    };
+    let head_history = if refs.is_none() && options.commits {
+        Some(
+            explorer
+                .commits(revision.unwrap_or("HEAD"))
+                .wrap_err("Unable to read the history")?,
+        )
+    } else {
+        None
+    };
+    let history = match &refs {
+        Some(refs) => Some(refs.commits.as_slice()),
+        None => head_history.as_deref(),
+    };
+    let workspace = discover_workspace(explorer, options, history)
+        .wrap_err("Unable to read the workspace manifest")?;
+
+    let mut results = match revision {
+        Some(revision) => revision_metrics(explorer, reader, revision, history, options)?,
+        None => worktree_metrics(explorer, reader, refs.as_ref(), history, options)?,
+    };
+    if options.blame {
+        load_blame(explorer, revision.unwrap_or("HEAD"), &mut results);
+    }
+    if let Some(commits) = history {
+        assign_history(&mut results, commits, options);
+    }
+
+    if let Some(workspace) = workspace {
+        workspace.check_packages(&options.packages)?;
+        results.assign_packages(|filename| workspace.package_for(filename).map(str::to_string));
+        results.retain_packages(&options.packages);
+    } else if !options.packages.is_empty() {
+        return Err(eyre!("--package requires a Cargo.toml at the project root"));
+    }
+
+    Ok(results)
+}
+
+/// Workspace of the analysed revision, or of the working tree
+fn discover_workspace(
+    explorer: &impl RepositoryExplorer,
+    options: &AnalysisOptions,
+    history: Option<&[CommitRecord]>,
+) -> Result<Option<Workspace>> {
+    let Some(revision) = &options.revision else {
+        return Workspace::discover(&options.project_path);
+    };
+    let paths: BTreeSet<String> = match history {
+        Some(commits) => commits
+            .iter()
+            .flat_map(|commit| commit.files.iter().cloned())
+            .collect(),
+        None => explorer.change_count_per_file()?.into_keys().collect(),
+    };
+    Workspace::discover_at(paths.iter().map(String::as_str), |path| {
+        explorer.file_content(revision, path)
+    })
+}
+
+/// Metrics of the files of `revision`, with the churn of `history` when it was read
+fn revision_metrics(
+    explorer: &impl RepositoryExplorer,
+    reader: &impl MetricReader,
+    revision: &str,
+    history: Option<&[CommitRecord]>,
+    options: &AnalysisOptions,
+) -> Result<ProjectMetrics> {
+    let mut results = match history {
+        Some(commits) => metrics_from_churn_at(explorer, reader, revision, count_changes(commits))?,
+        None => get_metrics_at(explorer, reader, revision)?,
+    };
+    if options.functions {
+        results.load_functions_with(|filename| match explorer.file_content(revision, filename) {
+            Ok(Some(content)) => reader.get_functions_from_content(Path::new(filename), content),
+            _ => vec![],
+        });
+    }
+    if options.bugfixes.is_some() {
+        results.load_lines_with(|filename| match explorer.file_content(revision, filename) {
+            Ok(Some(content)) => reader.get_lines_from_content(Path::new(filename), content),
+            _ => None,
+        });
+    }
+    Ok(results)
+}
+
+/// Metrics of the files of the working tree, with the churn of the commits of `refs` or of
+/// `history` when they were read
+fn worktree_metrics(
+    explorer: &impl RepositoryExplorer,
+    reader: &impl MetricReader,
+    refs: Option<&RefChurn>,
+    history: Option<&[CommitRecord]>,
+    options: &AnalysisOptions,
+) -> Result<ProjectMetrics> {
+    let mut results = match (refs, history) {
+        (Some(refs), _) => metrics_from_ref_churn(refs, reader),
+        (None, Some(commits)) if options.include_worktree => {
+            let explorer = WithWorktree::new(explorer)?;
+            let mut change_map = count_changes(commits);
+            for file in explorer.changes() {
+                *change_map.entry(file.clone()).or_default() += 1;
+            }
+            let mut results = metrics_from_churn(change_map, reader);
+            results.mark_local_changes(explorer.changes());
+            results
+        }
+        (None, Some(commits)) => metrics_from_churn(count_changes(commits), reader),
+        (None, None) => get_metrics(explorer, reader)?,
+    };
+    if options.functions {
+        results.load_functions(reader);
+    }
+    if options.bugfixes.is_some() {
+        results.load_lines(reader);
+    }
+    Ok(results)
+}
+
+/// Blame every file at `revision`, warning once about the files that cannot be blamed
+fn load_blame(explorer: &impl RepositoryExplorer, revision: &str, results: &mut ProjectMetrics) {
+    let mut failures = vec![];
+    results.load_blame_with(|filename| {
+        explorer.blame(revision, filename).unwrap_or_else(|error| {
+            failures.push((filename.to_string(), error));
+            vec![]
+        })
+    });
+    // Files added since the revision, such as uncommitted ones, cannot be blamed
+    if let Some((filename, error)) = failures.first() {
+        eprintln!(
+            "warning: unable to blame {filename} and {} other files, their owners are left out: {error:#}",
+            failures.len() - 1
+        );
+    }
+}
+
+/// Assign the metrics drawn from the commits themselves: fixes, dates, activity and the share
+/// of each file changed by departed authors
+fn assign_history(
+    results: &mut ProjectMetrics,
+    commits: &[CommitRecord],
+    options: &AnalysisOptions,
+) {
+    if let Some(classifier) = &options.bugfixes {
+        results.assign_bugfixes(&bugfix_counts(commits, classifier));
+    }
+    let newest = commits
+        .iter()
+        .map(|commit| commit.time)
+        .max()
+        .unwrap_or_default();
+    results.assign_dates(&file_dates(commits), newest);
+    results.assign_activity(
+        &recent_commits(commits, RECENT_COMMIT_COUNT),
+        &monthly_churn(commits, newest, ACTIVITY_MONTHS),
+    );
+
+    let active = match (&options.active_authors, options.active_months) {
+        (Some(active), _) => Some(active.clone()),
+        (None, Some(months)) => Some(ActiveAuthors::since(
+            commits,
+            newest - months * SECONDS_PER_MONTH,
+        )),
+        (None, None) => None,
+    };
+    if let Some(active) = active {
+        let mut shares = departed_shares(commits, &active);
+        // Blamed lines tell who still knows the code better than past changes
+        for metric in results.file_metrics() {
+            if let Some(share) = departed_line_share(&metric.owners, &active) {
+                shares.insert(metric.filename.clone(), share);
+            }
+        }
+        results.assign_departed_shares(&shares);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::Path};
+
+    use eyre::Result;
+    use tempfile::tempdir;
+
+    use crate::{git::CommitRecord, history::Classifier, Churn, MetricReader, RepositoryExplorer};
+
+    use super::{analyse, AnalysisOptions};
+
+    struct TestReader {}
+
+    impl MetricReader for TestReader {
+        fn get_cyclomatic_from_path_and_content(&self, _path: &Path) -> Option<f64> {
+            Some(1.0)
+        }
+    }
+
+    /// `release` holds a fix of `a.rs` that `main`, checked out, lacks
+    struct ReleaseExplorer {}
+
+    impl RepositoryExplorer for ReleaseExplorer {
+        fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+            Ok(HashMap::new())
+        }
+
+        fn commits(&self, revision: &str) -> Result<Vec<CommitRecord>> {
+            let mut feature = CommitRecord::new("1", "Test", &["a.rs"]);
+            feature.message = "feat: a".to_string();
+            let mut fix = CommitRecord::new("2", "Test", &["a.rs"]);
+            fix.message = "fix: a".to_string();
+            fix.time = 1;
+            Ok(match revision {
+                "refs/heads/release" => vec![fix, feature],
+                _ => vec![feature],
+            })
+        }
+
+        fn references(&self, _pattern: &str) -> Result<Vec<String>> {
+            Ok(vec![
+                "refs/heads/main".to_string(),
+                "refs/heads/release".to_string(),
+            ])
+        }
+    }
+
+    #[test]
+    fn refs_history_covers_every_reference() {
+        let dir = tempdir().expect("temp dir obtained");
+        let options = AnalysisOptions {
+            project_path: dir.path().to_path_buf(),
+            ref_patterns: vec!["refs/heads/*".to_string()],
+            commits: true,
+            bugfixes: Some(Classifier::new(&[]).expect("classifier")),
+            ..AnalysisOptions::default()
+        };
+
+        let results = analyse(&ReleaseExplorer {}, &TestReader {}, &options).expect("metrics");
+
+        let metric = &results.file_metrics()[0];
+        assert_eq!(metric.churn, Churn::from(2));
+        assert_eq!(metric.bugfixes, Some(1));
+        assert_eq!(metric.last_commit, Some(1));
+        assert_eq!(metric.recent_commits.len(), 2);
+    }
+}
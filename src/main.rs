@@ -1,39 +1,535 @@
-use clap::Parser;
-use eyre::{Context, Result};
-use std::{io, path::PathBuf};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use eyre::{eyre, Context, Result};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
 
 use quality_time::{
-    get_metrics,
-    git::Gitoxide,
-    metrics::CodeAnalysisReader,
-    output::{print_output, OutputMode},
+    coverage::Coverage,
+    filter::CommitFilter,
+    history::{ActiveAuthors, Classifier},
+    metrics::{CodeAnalysisReader, SortKey},
+    open_repository,
+    output::{
+        print_crap_report, print_explanation, print_knowledge_loss_report, print_output,
+        print_pr_report, read_json_report, OutputMode, OutputOptions,
+    },
+    pr::pull_request_impact,
+    thresholds::{Granularity, Thresholds},
+    vcs::{LogFile, Vcs},
+    AnalysisOptions, Churn, ProjectMetrics, RepositoryExplorer, RepositoryOptions,
 };
 
 /// Simple program to get complexity and churn metrics
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Rank functions by CRAP score, combining their complexity with their test coverage
+    Crap {
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+    },
+    /// Rank the files mostly changed by authors who are no longer active, weighted by complexity
+    KnowledgeLoss {
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+    },
+    /// Describe one file: its metrics, rank, quadrant, most complex functions and recent commits
+    Explain {
+        /// Path of the file, relative to the project root
+        #[arg(value_name = "PATH")]
+        path: String,
+
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+    },
+    /// Report the files changed on a branch, with their complexity before and after it
+    Pr {
+        /// Branch or revision the changes are going to be merged into
+        #[arg(long, value_name = "REVISION")]
+        base: String,
+
+        /// Tip of the changes to review
+        #[arg(long, value_name = "REVISION", default_value = "HEAD")]
+        head: String,
+
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+struct AnalysisArgs {
     /// Output style of the CLI
     #[arg(short, long, value_enum)]
     output: Option<OutputMode>,
 
+    /// Write the results to this file instead of the terminal
+    #[arg(long, value_name = "FILE")]
+    output_file: Option<PathBuf>,
+
     /// The path of the repository to analyse
-    #[arg(short, long, value_name = "PROJECT")]
+    #[arg(short, long, value_name = "PROJECT", default_value = ".")]
     project_path: PathBuf,
+
+    /// Version control backend reading the history
+    #[arg(long, value_enum, default_value_t = Vcs::Auto)]
+    vcs: Vcs,
+
+    /// Add the history of the checked out submodules, their files prefixed with their path
+    #[arg(long, conflicts_with = "rev")]
+    submodules: bool,
+
+    /// Fail instead of warning when the history is truncated, as in shallow clones
+    #[arg(long)]
+    require_full_history: bool,
+
+    /// Blame the analysed files to report who owns their current lines and how many commits
+    /// shaped each function, slow on large projects
+    #[arg(long, conflicts_with = "log_file")]
+    blame: bool,
+
+    /// Report the first and last change of each file, and its age in days before the newest
+    /// analysed commit, in the table and JSON outputs
+    #[arg(long, conflicts_with = "log_file")]
+    dates: bool,
+
+    /// File listing the names or emails of the active authors, one per line
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["active_months", "log_file"])]
+    active_authors: Option<PathBuf>,
+
+    /// Consider active the authors who committed in the last N months of the history
+    #[arg(long, value_name = "N", conflicts_with = "log_file")]
+    active_months: Option<i64>,
+
+    /// Leave merge commits out of the history. Merges list no file and never count in the churn,
+    /// so this only stops their authors from counting as active authors
+    #[arg(long, conflicts_with = "log_file")]
+    skip_merges: bool,
+
+    /// Leave out the commits whose author name or email contains this text, such as a bot
+    /// name; can be repeated
+    #[arg(
+        long = "exclude-author",
+        value_name = "NAME",
+        conflicts_with = "log_file"
+    )]
+    excluded_authors: Vec<String>,
+
+    /// Leave out the commits changing more files than this, such as formatting sweeps
+    #[arg(long, value_name = "N", conflicts_with = "log_file")]
+    max_files_per_commit: Option<usize>,
+
+    /// Leave out the commits listed in this file, such as a `.git-blame-ignore-revs`
+    #[arg(long, value_name = "FILE", conflicts_with = "log_file")]
+    ignore_revs_file: Option<PathBuf>,
+
+    /// `git log --numstat` output, or JSON export, to read the history from instead of a repository
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["vcs", "include_worktree"])]
+    log_file: Option<PathBuf>,
+
+    /// Tar archive of the sources described by the log file, read instead of the project files
+    #[arg(long, value_name = "ARCHIVE", requires = "log_file")]
+    source_archive: Option<PathBuf>,
+
+    /// Count the commits reachable from the references matching this pattern, such as
+    /// `refs/heads/release/*`, instead of `HEAD`; can be repeated. Mercurial branches and
+    /// bookmarks are named `refs/heads/<name>`
+    #[arg(
+        long = "refs",
+        value_name = "PATTERN",
+        conflicts_with_all = ["rev", "log_file", "include_worktree", "submodules"]
+    )]
+    ref_patterns: Vec<String>,
+
+    /// Analyse the history and files of this commit, tag or branch instead of the working tree
+    #[arg(long, value_name = "REVISION")]
+    rev: Option<String>,
+
+    /// Count uncommitted changes as one more commit and flag the files holding them
+    #[arg(long, conflicts_with = "rev")]
+    include_worktree: bool,
+
+    /// Count the fix commits of each file, recognised by their `fix:` prefix, and their defect density
+    #[arg(long, conflicts_with = "log_file")]
+    bugfixes: bool,
+
+    /// Regex marking the messages of fix commits, such as an issue key; can be repeated
+    #[arg(long = "fix-pattern", value_name = "REGEX", requires = "bugfixes")]
+    fix_patterns: Vec<String>,
+
+    /// Only analyse the files of the given workspace package, can be repeated
+    #[arg(long = "package", value_name = "NAME")]
+    packages: Vec<String>,
+
+    /// Number of top files labelled in charts and listed in reports
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    top: usize,
+
+    /// JSON report of a previous run to compare the results with
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<PathBuf>,
+
+    /// Report files or functions whose cyclomatic complexity is above this value
+    #[arg(long, value_name = "COMPLEXITY")]
+    max_complexity: Option<f64>,
+
+    /// Report files changed more times than this value
+    #[arg(long, value_name = "CHURN")]
+    max_churn: Option<i32>,
+
+    /// Report files or functions whose combined churn and complexity is above this value
+    #[arg(long, value_name = "MAGNITUDE")]
+    max_magnitude: Option<f64>,
+
+    /// Check the thresholds per file or per function
+    #[arg(long, value_enum, default_value_t = Granularity::File)]
+    granularity: Granularity,
+
+    /// lcov tracefile or Cobertura XML report to read the test coverage of each file from, which
+    /// describes the working tree
+    #[arg(long, value_name = "FILE", conflicts_with = "rev")]
+    coverage: Option<PathBuf>,
+
+    /// Order of the files, defaults to risk when coverage is given and magnitude otherwise
+    #[arg(long, value_enum)]
+    sort_by: Option<SortKey>,
+
+    /// Print one hotspot table per workspace package, followed by a summary of the packages
+    #[arg(long)]
+    per_crate: bool,
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let cli = Cli::parse();
+
+    match cli.command {
+        None => run_analysis(cli.analysis),
+        Some(Command::Crap { analysis }) => {
+            if analysis.coverage.is_none() {
+                return Err(eyre!("the crap report needs a --coverage report"));
+            }
+            let results = analyse(&analysis, true)?.metrics;
+            print_crap_report(&results, analysis.top, output_writer(&analysis)?)
+        }
+        Some(Command::Explain { path, analysis }) => {
+            let results = analyse(&analysis, true)?.metrics;
+            let path = path.trim_start_matches("./").replace('\\', "/");
+            print_explanation(&results, &path, output_writer(&analysis)?)
+        }
+        Some(Command::KnowledgeLoss { analysis }) => {
+            if analysis.active_authors.is_none() && analysis.active_months.is_none() {
+                return Err(eyre!(
+                    "the knowledge-loss report needs --active-authors or --active-months"
+                ));
+            }
+            let results = analyse(&analysis, false)?.metrics;
+            print_knowledge_loss_report(&results, analysis.top, output_writer(&analysis)?)
+        }
+        Some(Command::Pr {
+            base,
+            head,
+            mut analysis,
+        }) => {
+            if analysis.rev.is_some() {
+                return Err(eyre!("the pr report analyses --head, drop --rev"));
+            }
+            // Rank the files as they are at the head of the branch, not in the working tree
+            analysis.rev = Some(head.clone());
+            let Analysis {
+                metrics, explorer, ..
+            } = analyse(&analysis, false)?;
+            let impact = pull_request_impact(
+                &explorer,
+                &CodeAnalysisReader::default(),
+                &metrics,
+                &base,
+                &head,
+            )?;
+            print_pr_report(
+                analysis.output.unwrap_or(OutputMode::StdOut),
+                &impact,
+                output_writer(&analysis)?,
+            )
+        }
+    }
+}
+
+fn run_analysis(args: AnalysisArgs) -> Result<()> {
     let output = args.output.unwrap_or(OutputMode::StdOut);
-    let stdout = io::stdout();
-    let git_explorer =
-        Gitoxide::try_new(args.project_path).wrap_err("Unable to initialise repository")?;
+    // The details of the terminal application list the most complex functions
+    let mut analysis = analyse(&args, matches!(output, OutputMode::Tui))?;
+    // The terminal application colours the files by age
+    if !args.dates && !matches!(output, OutputMode::Tui) {
+        analysis.metrics.clear_dates();
+    }
 
-    let reader = CodeAnalysisReader::default();
+    let baseline = match &args.baseline {
+        Some(path) => {
+            let file =
+                File::open(path).wrap_err(format!("Unable to open baseline {}", path.display()))?;
+            Some(read_json_report(BufReader::new(file))?)
+        }
+        None => None,
+    };
 
-    let results = get_metrics(git_explorer, reader)?;
+    let options = OutputOptions {
+        per_package: args.per_crate,
+        configuration: configuration(&args, output, analysis.excluded.as_deref()),
+        top: args.top,
+        baseline,
+        thresholds: Thresholds {
+            complexity: args.max_complexity,
+            churn: args.max_churn.map(Churn::from),
+            magnitude: args.max_magnitude,
+        },
+        granularity: args.granularity,
+        sources_on_disk: args.rev.is_none() && args.source_archive.is_none(),
+    };
 
-    print_output(output, results, stdout)?;
+    print_output(output, analysis.metrics, options, output_writer(&args)?)
+}
+
+/// Metrics of the project and how the history was read
+struct Analysis {
+    metrics: ProjectMetrics,
+    /// Explorer the metrics were read with, for the reports reading more of the repository
+    explorer: Box<dyn RepositoryExplorer>,
+    /// Number of commits left out of the history, by reason
+    excluded: Option<String>,
+}
+
+/// Collect the metrics of the project, restricted, enriched and sorted as asked, with the
+/// functions of every file when `needs_functions` or the arguments need them
+fn analyse(args: &AnalysisArgs, needs_functions: bool) -> Result<Analysis> {
+    check_sort_key(args)?;
+    let (explorer, exclusions): (Box<dyn RepositoryExplorer>, _) = match &args.log_file {
+        Some(path) => {
+            let log = LogFile::read(path)?;
+            let explorer: Box<dyn RepositoryExplorer> = match &args.source_archive {
+                Some(archive) => Box::new(log.with_archive(archive)?),
+                None => Box::new(log),
+            };
+            (explorer, None)
+        }
+        None => {
+            let options = RepositoryOptions {
+                vcs: args.vcs,
+                require_full_history: args.require_full_history,
+                submodules: args.submodules,
+                filter: commit_filter(args)?,
+            };
+            open_repository(args.project_path.clone(), &options)?
+        }
+    };
+    let options = AnalysisOptions {
+        project_path: args.project_path.clone(),
+        // The files of an archive are read like the ones of a revision
+        revision: match (&args.rev, &args.source_archive) {
+            (Some(revision), _) => Some(revision.clone()),
+            (None, Some(_)) => Some("HEAD".to_string()),
+            (None, None) => None,
+        },
+        ref_patterns: args.ref_patterns.clone(),
+        include_worktree: args.include_worktree,
+        // Exported logs only give the files changed by each commit
+        commits: args.log_file.is_none(),
+        functions: needs_functions
+            || args.granularity == Granularity::Function
+            || args.coverage.is_some(),
+        bugfixes: args
+            .bugfixes
+            .then(|| Classifier::new(&args.fix_patterns))
+            .transpose()?,
+        blame: args.blame,
+        active_authors: args
+            .active_authors
+            .as_deref()
+            .map(ActiveAuthors::read)
+            .transpose()?,
+        active_months: args.active_months,
+        packages: args.packages.clone(),
+    };
+    let mut results = quality_time::analyse(&explorer, &CodeAnalysisReader::default(), &options)?;
+    let excluded = exclusions.and_then(|log| log.summary());
+    if let Some(summary) = &excluded {
+        eprintln!("note: {summary}");
+    }
+
+    if let Some(path) = &args.coverage {
+        results.apply_coverage(&Coverage::read(path)?);
+    }
+    let default_sort = match args.coverage {
+        Some(_) => SortKey::Risk,
+        None => SortKey::Magnitude,
+    };
+    results.sort_by(args.sort_by.unwrap_or(default_sort));
+
+    Ok(Analysis {
+        metrics: results,
+        explorer,
+        excluded,
+    })
+}
+
+/// Fail when `--sort-by` names a metric the other arguments leave empty, as every file would
+/// keep its place
+fn check_sort_key(args: &AnalysisArgs) -> Result<()> {
+    let missing = match args.sort_by {
+        Some(SortKey::Risk) if args.coverage.is_none() => "a --coverage report",
+        Some(SortKey::Bugfixes | SortKey::DefectDensity) if !args.bugfixes => "--bugfixes",
+        Some(SortKey::Recency) if args.log_file.is_some() => {
+            "the commit dates of a repository, which a --log-file lacks"
+        }
+        Some(SortKey::KnowledgeLoss)
+            if args.active_authors.is_none() && args.active_months.is_none() =>
+        {
+            "--active-authors or --active-months"
+        }
+        _ => return Ok(()),
+    };
+    let key = args
+        .sort_by
+        .and_then(|key| key.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+    Err(eyre!("--sort-by {key} needs {missing}"))
+}
+
+/// Commits to leave out of the history, as asked on the command line
+fn commit_filter(args: &AnalysisArgs) -> Result<CommitFilter> {
+    let mut filter = CommitFilter {
+        skip_merges: args.skip_merges,
+        authors: args.excluded_authors.clone(),
+        max_files: args.max_files_per_commit,
+        ignored_revisions: vec![],
+    };
+    if let Some(path) = &args.ignore_revs_file {
+        filter.read_ignored_revisions(path)?;
+    }
+    Ok(filter)
+}
+
+/// The output file when one is given, the terminal otherwise
+fn output_writer(args: &AnalysisArgs) -> Result<Box<dyn Write>> {
+    match &args.output_file {
+        Some(path) => {
+            let file = File::create(path)
+                .wrap_err(format!("Unable to create output file {}", path.display()))?;
+            Ok(Box::new(BufWriter::new(file)))
+        }
+        None => Ok(Box::new(io::stdout())),
+    }
+}
 
-    Ok(())
+/// Settings of this run, shown in the reports, with the summary of the `excluded` commits
+fn configuration(
+    args: &AnalysisArgs,
+    output: OutputMode,
+    excluded: Option<&str>,
+) -> Vec<(String, String)> {
+    let mut configuration = vec![
+        (
+            "Project".to_string(),
+            args.project_path.display().to_string(),
+        ),
+        ("Output".to_string(), format!("{output:?}")),
+        ("Version".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+    ];
+    if let Some(revision) = &args.rev {
+        configuration.push(("Revision".to_string(), revision.clone()));
+    }
+    if !args.ref_patterns.is_empty() {
+        configuration.push(("References".to_string(), args.ref_patterns.join(", ")));
+    }
+    if let Some(log_file) = &args.log_file {
+        configuration.push(("Log file".to_string(), log_file.display().to_string()));
+    }
+    if let Some(archive) = &args.source_archive {
+        configuration.push(("Source archive".to_string(), archive.display().to_string()));
+    }
+    let mut exclusions = vec![];
+    if args.skip_merges {
+        exclusions.push("merges".to_string());
+    }
+    if !args.excluded_authors.is_empty() {
+        exclusions.push(format!("authors {}", args.excluded_authors.join(", ")));
+    }
+    if let Some(max) = args.max_files_per_commit {
+        exclusions.push(format!("more than {max} files"));
+    }
+    if let Some(path) = &args.ignore_revs_file {
+        exclusions.push(format!("revisions of {}", path.display()));
+    }
+    if !exclusions.is_empty() {
+        configuration.push(("Excluded commits".to_string(), exclusions.join("; ")));
+    }
+    if let Some(summary) = excluded {
+        configuration.push(("Exclusions".to_string(), summary.to_string()));
+    }
+    if args.include_worktree {
+        configuration.push(("Working tree".to_string(), "included".to_string()));
+    }
+    if args.bugfixes {
+        let mut classification = "fix: prefix".to_string();
+        for pattern in &args.fix_patterns {
+            classification.push_str(&format!(", {pattern}"));
+        }
+        configuration.push(("Fix commits".to_string(), classification));
+    }
+    if args.blame {
+        configuration.push(("Line ownership".to_string(), "blame".to_string()));
+    }
+    if let Some(path) = &args.active_authors {
+        configuration.push(("Active authors".to_string(), path.display().to_string()));
+    }
+    if let Some(months) = args.active_months {
+        configuration.push((
+            "Active authors".to_string(),
+            format!("committed in the last {months} months"),
+        ));
+    }
+    if !args.packages.is_empty() {
+        configuration.push(("Packages".to_string(), args.packages.join(", ")));
+    }
+    if let Some(baseline) = &args.baseline {
+        configuration.push(("Baseline".to_string(), baseline.display().to_string()));
+    }
+    if let Some(coverage) = &args.coverage {
+        configuration.push(("Coverage".to_string(), coverage.display().to_string()));
+    }
+    let thresholds = [
+        (
+            "Maximum complexity",
+            args.max_complexity.map(|v| v.to_string()),
+        ),
+        ("Maximum churn", args.max_churn.map(|v| v.to_string())),
+        (
+            "Maximum magnitude",
+            args.max_magnitude.map(|v| v.to_string()),
+        ),
+    ];
+    for (label, value) in thresholds {
+        if let Some(value) = value {
+            configuration.push((label.to_string(), value));
+        }
+    }
+    configuration
 }
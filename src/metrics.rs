@@ -1,12 +1,78 @@
-use std::{collections::HashMap, fmt::Display, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Display,
+    ops::AddAssign,
+    path::Path,
+};
 
-use rust_code_analysis::{metrics, read_file_with_eol, CodeMetrics, ParserTrait, RustParser};
+use clap::ValueEnum;
+
+use rust_code_analysis::{
+    metrics, read_file_with_eol, CodeMetrics, FuncSpace, ParserTrait, RustParser, SpaceKind,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::coverage::{Coverage, FileCoverage};
+use crate::git::count_owners;
+use crate::history::FileDates;
 
 pub trait MetricReader {
     fn get_cyclomatic_from_path_and_content(&self, path: &Path) -> Option<f64>;
+
+    /// Metrics of every function defined in the file, empty when unsupported
+    fn get_functions_from_path(&self, _path: &Path) -> Vec<FunctionMetrics> {
+        vec![]
+    }
+
+    /// Complexity of `content`, as if it was the content of the file at `path`
+    fn get_cyclomatic_from_content(&self, _path: &Path, _content: Vec<u8>) -> Option<f64> {
+        None
+    }
+
+    /// Like [`Self::get_functions_from_path`], reading the functions from `content`
+    fn get_functions_from_content(&self, _path: &Path, _content: Vec<u8>) -> Vec<FunctionMetrics> {
+        vec![]
+    }
+
+    /// Source lines of code of the file, `None` when unsupported
+    fn get_lines_from_path(&self, _path: &Path) -> Option<f64> {
+        None
+    }
+
+    /// Like [`Self::get_lines_from_path`], counting the lines of `content`
+    fn get_lines_from_content(&self, _path: &Path, _content: Vec<u8>) -> Option<f64> {
+        None
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+impl<R: MetricReader> MetricReader for &R {
+    fn get_cyclomatic_from_path_and_content(&self, path: &Path) -> Option<f64> {
+        (*self).get_cyclomatic_from_path_and_content(path)
+    }
+
+    fn get_functions_from_path(&self, path: &Path) -> Vec<FunctionMetrics> {
+        (*self).get_functions_from_path(path)
+    }
+
+    fn get_cyclomatic_from_content(&self, path: &Path, content: Vec<u8>) -> Option<f64> {
+        (*self).get_cyclomatic_from_content(path, content)
+    }
+
+    fn get_functions_from_content(&self, path: &Path, content: Vec<u8>) -> Vec<FunctionMetrics> {
+        (*self).get_functions_from_content(path, content)
+    }
+
+    fn get_lines_from_path(&self, path: &Path) -> Option<f64> {
+        (*self).get_lines_from_path(path)
+    }
+
+    fn get_lines_from_content(&self, path: &Path, content: Vec<u8>) -> Option<f64> {
+        (*self).get_lines_from_content(path, content)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Churn(i32);
 
 impl Churn {
@@ -21,6 +87,12 @@ impl From<i32> for Churn {
     }
 }
 
+impl AddAssign<i32> for Churn {
+    fn add_assign(&mut self, changes: i32) {
+        self.0 += changes;
+    }
+}
+
 impl Display for Churn {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.0.fmt(f)
@@ -45,8 +117,38 @@ impl CodeAnalysisReader {
         content: Option<Vec<u8>>,
         path: &Path,
     ) -> Option<CodeMetrics> {
+        self.space_from_path_and_content(content, path)
+            .map(|v| v.metrics)
+    }
+
+    fn space_from_path_and_content(
+        &self,
+        content: Option<Vec<u8>>,
+        path: &Path,
+    ) -> Option<FuncSpace> {
         let parser = RustParser::new(content.expect("file content"), path, None);
-        metrics(&parser, path).map(|v| v.metrics)
+        metrics(&parser, path)
+    }
+}
+
+/// Flatten the function spaces nested anywhere below `space`
+fn collect_functions(space: &FuncSpace, functions: &mut Vec<FunctionMetrics>) {
+    for child in &space.spaces {
+        if child.kind == SpaceKind::Function {
+            functions.push(FunctionMetrics {
+                name: child
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| "<anonymous>".to_string()),
+                start_line: child.start_line,
+                end_line: child.end_line,
+                cyclomatic: child.metrics.cyclomatic.cyclomatic(),
+                cognitive: child.metrics.cognitive.cognitive(),
+                coverage: None,
+                blame_commits: None,
+            });
+        }
+        collect_functions(child, functions);
     }
 }
 
@@ -57,20 +159,283 @@ impl MetricReader for CodeAnalysisReader {
             .and_then(|file| self.metric_from_path_and_content(file, path))
             .map(|metrics| metrics.cyclomatic.cyclomatic_sum())
     }
+
+    fn get_cyclomatic_from_content(&self, path: &Path, content: Vec<u8>) -> Option<f64> {
+        self.metric_from_path_and_content(Some(content), path)
+            .map(|metrics| metrics.cyclomatic.cyclomatic_sum())
+    }
+
+    fn get_functions_from_path(&self, path: &Path) -> Vec<FunctionMetrics> {
+        match read_file_with_eol(path).ok().flatten() {
+            Some(content) => self.get_functions_from_content(path, content),
+            None => vec![],
+        }
+    }
+
+    fn get_functions_from_content(&self, path: &Path, content: Vec<u8>) -> Vec<FunctionMetrics> {
+        let mut functions = vec![];
+        if let Some(space) = self.space_from_path_and_content(Some(content), path) {
+            collect_functions(&space, &mut functions);
+        }
+        functions.sort_by_key(|function| function.start_line);
+        functions
+    }
+
+    fn get_lines_from_path(&self, path: &Path) -> Option<f64> {
+        read_file_with_eol(path)
+            .ok()
+            .flatten()
+            .and_then(|content| self.get_lines_from_content(path, content))
+    }
+
+    fn get_lines_from_content(&self, path: &Path, content: Vec<u8>) -> Option<f64> {
+        self.metric_from_path_and_content(Some(content), path)
+            .map(|metrics| metrics.loc.sloc())
+    }
+}
+
+/// Last change of one line of a file, according to blame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlamedLine {
+    pub commit: String,
+    pub author: String,
+    pub email: String,
 }
 
+/// Lines of a file last changed by one author, according to blame
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineOwner {
+    pub author: String,
+    pub email: String,
+    pub lines: usize,
+}
+
+#[cfg(test)]
+impl LineOwner {
+    /// `lines` lines owned by `author`, whose email is made from the name
+    pub fn new(author: &str, lines: usize) -> Self {
+        Self {
+            author: author.to_string(),
+            email: format!("{author}@example.com"),
+            lines,
+        }
+    }
+}
+
+/// Everything known about one file, and how it compares to the others
 #[derive(Debug, Clone, PartialEq)]
+pub struct Explanation<'a> {
+    pub metric: &'a FileMetrics,
+    /// Position of the file by decreasing magnitude, from 1
+    pub rank: usize,
+    pub file_count: usize,
+    /// Share of the files whose magnitude is lower or equal, between 0 and 1
+    pub percentile: f64,
+    pub quadrant: Quadrant,
+    /// Most complex functions of the file, by decreasing cyclomatic complexity
+    pub top_functions: Vec<&'a FunctionMetrics>,
+}
+
+/// A commit changing a file, as listed in its details
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitSummary {
+    pub id: String,
+    /// Author date, in seconds since the Unix epoch
+    pub time: i64,
+    pub author: String,
+    /// First line of the commit message
+    pub summary: String,
+}
+
+/// Line owners of the files of a directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryOwners {
+    pub directory: String,
+    /// By decreasing number of lines
+    pub owners: Vec<LineOwner>,
+}
+
+impl DirectoryOwners {
+    pub fn total_lines(&self) -> usize {
+        self.owners.iter().map(|owner| owner.lines).sum()
+    }
+}
+
+/// Complexity of a single function of a file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionMetrics {
+    pub name: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub cyclomatic: f64,
+    pub cognitive: f64,
+    /// Share of the function lines covered by tests, between 0 and 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<f64>,
+    /// Number of distinct commits that last changed the function lines, according to blame
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blame_commits: Option<usize>,
+}
+
+#[cfg(test)]
+impl FunctionMetrics {
+    /// Function of the lines `start_line` to `end_line`, without cognitive complexity, coverage or
+    /// blame
+    pub fn new(name: &str, start_line: usize, end_line: usize, cyclomatic: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            start_line,
+            end_line,
+            cyclomatic,
+            cognitive: 0.0,
+            coverage: None,
+            blame_commits: None,
+        }
+    }
+}
+
+impl FunctionMetrics {
+    /// CRAP score of the function, `None` without coverage data
+    pub fn crap(&self) -> Option<f64> {
+        self.coverage
+            .map(|coverage| crap(self.cyclomatic, coverage))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileMetrics {
     pub filename: String,
     pub churn: Churn,
     pub complexity: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<FunctionMetrics>,
+    /// Share of lines covered by tests, between 0 and 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<f64>,
+    /// Share of branches taken by tests, between 0 and 1, when the report has branch data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_coverage: Option<f64>,
+    /// Churn reachable from each analysed reference, when several are
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub churn_per_ref: BTreeMap<String, Churn>,
+    /// Whether the working tree holds uncommitted changes of the file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locally_modified: bool,
+    /// Number of fix commits changing the file, when commits were classified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bugfixes: Option<usize>,
+    /// Source lines of code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<f64>,
+    /// Date of the first commit changing the file, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_commit: Option<i64>,
+    /// Date of the last commit changing the file, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_commit: Option<i64>,
+    /// Days since the last commit changing the file, up to the newest analysed commit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_days: Option<i64>,
+    /// Share of the changes made by authors who are no longer active, between 0 and 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub departed_share: Option<f64>,
+    /// Authors of the current lines, by decreasing number of lines
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<LineOwner>,
+    /// Latest commits changing the file, newest first, only shown by the explanations
+    #[serde(skip)]
+    pub recent_commits: Vec<CommitSummary>,
+    /// Commits changing the file in each of the last months of the history, oldest first, only
+    /// shown by the explanations
+    #[serde(skip)]
+    pub monthly_churn: Vec<u64>,
+}
+
+/// Order of the analysed files, highest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    /// Combined churn and complexity
+    #[default]
+    Magnitude,
+    /// Combined churn and CRAP score, needs coverage data
+    Risk,
+    /// Number of fix commits, needs `--bugfixes`
+    Bugfixes,
+    /// Fix commits per thousand lines of code, needs `--bugfixes`
+    DefectDensity,
+    /// Most recently changed first
+    Recency,
+    /// Complexity weighted by the share of changes from departed authors, needs active authors
+    KnowledgeLoss,
+}
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// CRAP score: complexity weighted by the share of code left untested
+pub fn crap(complexity: f64, coverage: f64) -> f64 {
+    complexity.powi(2) * (1.0 - coverage).powi(3) + complexity
+}
+
+/// Aggregated metrics of every analysed file belonging to one package of the workspace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageSummary {
+    pub name: String,
+    pub file_count: usize,
+    pub churn_sum: f64,
+    pub complexity_sum: f64,
+    pub hotspots: Vec<FileMetrics>,
+}
+
+/// Position of a file relative to the average churn and complexity of the project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Quadrant {
+    /// Churn and complexity both above average: refactor these first
+    Hotspot,
+    /// Complex code that rarely changes
+    ComplexStable,
+    /// Simple code that changes often
+    ChurningSimple,
+    /// Below average churn and complexity
+    Healthy,
 }
 
-#[derive(Debug, Clone)]
+impl Quadrant {
+    pub const ALL: [Quadrant; 4] = [
+        Quadrant::Hotspot,
+        Quadrant::ComplexStable,
+        Quadrant::ChurningSimple,
+        Quadrant::Healthy,
+    ];
+}
+
+impl Display for Quadrant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Quadrant::Hotspot => "Hotspot",
+            Quadrant::ComplexStable => "Complex but stable",
+            Quadrant::ChurningSimple => "Churning but simple",
+            Quadrant::Healthy => "Healthy",
+        };
+        f.write_str(label)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectMetrics {
+    #[serde(rename = "files")]
     file_metrics: Vec<FileMetrics>,
 }
 
+/// Difference of one file's metrics between a baseline run and the current run
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricChange {
+    pub filename: String,
+    pub before: Option<FileMetrics>,
+    pub after: Option<FileMetrics>,
+}
+
 impl ProjectMetrics {
     pub fn new(metrics: Vec<FileMetrics>) -> Self {
         Self {
@@ -105,6 +470,405 @@ impl ProjectMetrics {
     pub fn complexity_sum(&self) -> f64 {
         self.file_metrics.iter().map(|x| x.complexity).sum()
     }
+
+    pub fn churn_mean(&self) -> f64 {
+        if self.file_metrics.is_empty() {
+            return 0.0;
+        }
+        self.churn_sum() / self.file_metrics.len() as f64
+    }
+
+    pub fn complexity_mean(&self) -> f64 {
+        if self.file_metrics.is_empty() {
+            return 0.0;
+        }
+        self.complexity_sum() / self.file_metrics.len() as f64
+    }
+
+    pub fn quadrant_of(&self, metric: &FileMetrics) -> Quadrant {
+        let is_churning = metric.churn.as_f64() > self.churn_mean();
+        let is_complex = metric.complexity > self.complexity_mean();
+        match (is_churning, is_complex) {
+            (true, true) => Quadrant::Hotspot,
+            (false, true) => Quadrant::ComplexStable,
+            (true, false) => Quadrant::ChurningSimple,
+            (false, false) => Quadrant::Healthy,
+        }
+    }
+
+    /// Number of files in each quadrant, in the order of [`Quadrant::ALL`].
+    pub fn quadrant_counts(&self) -> Vec<(Quadrant, usize)> {
+        Quadrant::ALL
+            .iter()
+            .map(|quadrant| {
+                let count = self
+                    .file_metrics
+                    .iter()
+                    .filter(|metric| self.quadrant_of(metric) == *quadrant)
+                    .count();
+                (*quadrant, count)
+            })
+            .collect()
+    }
+
+    pub fn get(&self, filename: &str) -> Option<&FileMetrics> {
+        self.file_metrics.iter().find(|x| x.filename == filename)
+    }
+
+    /// Files that appeared, disappeared or whose churn or complexity moved since `baseline`,
+    /// in the order of the current results followed by the removed files.
+    pub fn changes_since(&self, baseline: &ProjectMetrics) -> Vec<MetricChange> {
+        let mut changes: Vec<MetricChange> = self
+            .file_metrics
+            .iter()
+            .filter_map(|after| {
+                let before = baseline.get(&after.filename);
+                let unchanged = before.is_some_and(|before| {
+                    before.churn == after.churn && before.complexity == after.complexity
+                });
+                (!unchanged).then(|| MetricChange {
+                    filename: after.filename.clone(),
+                    before: before.cloned(),
+                    after: Some(after.clone()),
+                })
+            })
+            .collect();
+
+        changes.extend(
+            baseline
+                .file_metrics
+                .iter()
+                .filter(|before| self.get(&before.filename).is_none())
+                .map(|before| MetricChange {
+                    filename: before.filename.clone(),
+                    before: Some(before.clone()),
+                    after: None,
+                }),
+        );
+
+        changes
+    }
+
+    pub fn has_coverage(&self) -> bool {
+        self.file_metrics.iter().any(|x| x.coverage.is_some())
+    }
+
+    pub fn has_branch_coverage(&self) -> bool {
+        self.file_metrics
+            .iter()
+            .any(|x| x.branch_coverage.is_some())
+    }
+
+    /// Attach the line coverage of every file found in `coverage`, and of their functions
+    /// when they were loaded beforehand
+    pub fn apply_coverage(&mut self, coverage: &Coverage) {
+        for metric in self.file_metrics.iter_mut() {
+            let file_coverage = coverage.for_file(&metric.filename);
+            metric.coverage = file_coverage.map(FileCoverage::line_rate);
+            metric.branch_coverage = file_coverage.and_then(FileCoverage::branch_rate);
+            for function in metric.functions.iter_mut() {
+                function.coverage = file_coverage.and_then(|file| {
+                    file.line_rate_between(function.start_line, function.end_line)
+                });
+            }
+        }
+    }
+
+    /// Every function with coverage data, by decreasing CRAP score
+    pub fn functions_by_crap(&self) -> Vec<(&FileMetrics, &FunctionMetrics)> {
+        let mut functions: Vec<_> = self
+            .file_metrics
+            .iter()
+            .flat_map(|file| file.functions.iter().map(move |function| (file, function)))
+            .filter(|(_, function)| function.crap().is_some())
+            .collect();
+        functions.sort_by(|(_, a), (_, b)| {
+            b.crap()
+                .unwrap_or_default()
+                .total_cmp(&a.crap().unwrap_or_default())
+        });
+        functions
+    }
+
+    /// Sort the files by decreasing `key`, files without a value for it coming last
+    pub fn sort_by(&mut self, key: SortKey) {
+        let value = |metric: &FileMetrics| match key {
+            SortKey::Magnitude => Some(metric.magnitude()),
+            SortKey::Risk => metric.risk(),
+            SortKey::Bugfixes => metric.bugfixes.map(|count| count as f64),
+            SortKey::DefectDensity => metric.defect_density(),
+            SortKey::Recency => metric.last_commit.map(|date| date as f64),
+            SortKey::KnowledgeLoss => metric.knowledge_loss(),
+        };
+        self.file_metrics
+            .sort_by(|a, b| match (value(a), value(b)) {
+                (Some(a), Some(b)) => b.total_cmp(&a),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            });
+    }
+
+    /// Read the per-function metrics of every file with `reader`
+    pub fn load_functions(&mut self, reader: &impl MetricReader) {
+        self.load_functions_with(|filename| reader.get_functions_from_path(Path::new(filename)));
+    }
+
+    /// Set the functions of every file to the ones returned by `functions_of`.
+    pub fn load_functions_with(&mut self, functions_of: impl Fn(&str) -> Vec<FunctionMetrics>) {
+        for metric in self.file_metrics.iter_mut() {
+            metric.functions = functions_of(&metric.filename);
+        }
+    }
+
+    /// Read the source lines of code of every file with `reader`
+    pub fn load_lines(&mut self, reader: &impl MetricReader) {
+        self.load_lines_with(|filename| reader.get_lines_from_path(Path::new(filename)));
+    }
+
+    /// Set the lines of code of every file to the ones returned by `lines_of`.
+    pub fn load_lines_with(&mut self, lines_of: impl Fn(&str) -> Option<f64>) {
+        for metric in self.file_metrics.iter_mut() {
+            metric.lines = lines_of(&metric.filename);
+        }
+    }
+
+    /// Set the number of fix commits of every file, files missing from `counts` having none
+    pub fn assign_bugfixes(&mut self, counts: &HashMap<String, usize>) {
+        for metric in self.file_metrics.iter_mut() {
+            metric.bugfixes = Some(counts.get(&metric.filename).copied().unwrap_or_default());
+        }
+    }
+
+    /// Set the first and last commit dates of every file, and their age at `now`
+    pub fn assign_dates(&mut self, dates: &HashMap<String, FileDates>, now: i64) {
+        for metric in self.file_metrics.iter_mut() {
+            let dates = dates.get(&metric.filename);
+            metric.first_commit = dates.map(|dates| dates.first);
+            metric.last_commit = dates.map(|dates| dates.last);
+            metric.age_days = dates.map(|dates| (now - dates.last).max(0) / SECONDS_PER_DAY);
+        }
+    }
+
+    /// Set the line owners of every file, and the commits blamed for its functions, from the lines
+    /// returned by `blame_of`.
+    pub fn load_blame_with(&mut self, mut blame_of: impl FnMut(&str) -> Vec<BlamedLine>) {
+        for metric in self.file_metrics.iter_mut() {
+            let lines = blame_of(&metric.filename);
+            metric.owners = count_owners(&lines);
+            for function in metric.functions.iter_mut() {
+                // Function lines are numbered from 1
+                let commits: HashSet<_> = lines
+                    .iter()
+                    .take(function.end_line)
+                    .skip(function.start_line.saturating_sub(1))
+                    .map(|line| &line.commit)
+                    .collect();
+                function.blame_commits = (!commits.is_empty()).then_some(commits.len());
+            }
+        }
+    }
+
+    /// Details of `filename`, `None` when it was not analysed
+    pub fn explain(&self, filename: &str, function_count: usize) -> Option<Explanation<'_>> {
+        let metric = self.get(filename)?;
+        let magnitude = metric.magnitude();
+        let file_count = self.file_metrics.len();
+        let higher = self
+            .file_metrics
+            .iter()
+            .filter(|other| other.magnitude() > magnitude)
+            .count();
+
+        let mut top_functions: Vec<_> = metric.functions.iter().collect();
+        top_functions.sort_by(|a, b| b.cyclomatic.total_cmp(&a.cyclomatic));
+        top_functions.truncate(function_count);
+
+        Some(Explanation {
+            metric,
+            rank: higher + 1,
+            file_count,
+            percentile: (file_count - higher) as f64 / file_count as f64,
+            quadrant: self.quadrant_of(metric),
+            top_functions,
+        })
+    }
+
+    /// Set the latest commits of every file, and the number of commits per month
+    pub fn assign_activity(
+        &mut self,
+        recent_commits: &HashMap<String, Vec<CommitSummary>>,
+        monthly_churn: &HashMap<String, Vec<u64>>,
+    ) {
+        for metric in self.file_metrics.iter_mut() {
+            metric.recent_commits = recent_commits
+                .get(&metric.filename)
+                .cloned()
+                .unwrap_or_default();
+            metric.monthly_churn = monthly_churn
+                .get(&metric.filename)
+                .cloned()
+                .unwrap_or_default();
+        }
+    }
+
+    pub fn has_owners(&self) -> bool {
+        self.file_metrics.iter().any(|x| !x.owners.is_empty())
+    }
+
+    /// Line owners of the files of each directory, not counting subdirectories, by directory name
+    pub fn directory_owners(&self) -> Vec<DirectoryOwners> {
+        let mut directories: BTreeMap<&str, HashMap<(&str, &str), usize>> = BTreeMap::new();
+        for metric in &self.file_metrics {
+            let directory = metric
+                .filename
+                .rsplit_once('/')
+                .map(|(directory, _)| directory)
+                .unwrap_or(".");
+            let owners = directories.entry(directory).or_default();
+            for owner in &metric.owners {
+                *owners.entry((&owner.author, &owner.email)).or_default() += owner.lines;
+            }
+        }
+
+        directories
+            .into_iter()
+            .filter(|(_, owners)| !owners.is_empty())
+            .map(|(directory, owners)| {
+                let mut owners: Vec<_> = owners
+                    .into_iter()
+                    .map(|((author, email), lines)| LineOwner {
+                        author: author.to_string(),
+                        email: email.to_string(),
+                        lines,
+                    })
+                    .collect();
+                owners.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.author.cmp(&b.author)));
+                DirectoryOwners {
+                    directory: directory.to_string(),
+                    owners,
+                }
+            })
+            .collect()
+    }
+
+    /// Set the share of the changes of every file made by departed authors
+    pub fn assign_departed_shares(&mut self, shares: &HashMap<String, f64>) {
+        for metric in self.file_metrics.iter_mut() {
+            metric.departed_share = shares.get(&metric.filename).copied();
+        }
+    }
+
+    /// Files mostly changed by departed authors, by decreasing knowledge loss
+    pub fn files_by_knowledge_loss(&self) -> Vec<&FileMetrics> {
+        let mut files: Vec<_> = self
+            .file_metrics
+            .iter()
+            .filter(|metric| metric.departed_share.is_some_and(|share| share > 0.5))
+            .collect();
+        files.sort_by(|a, b| {
+            b.knowledge_loss()
+                .unwrap_or_default()
+                .total_cmp(&a.knowledge_loss().unwrap_or_default())
+        });
+        files
+    }
+
+    /// Forget the commit dates, once the files are sorted, for the reports not asked to show them
+    pub fn clear_dates(&mut self) {
+        for metric in self.file_metrics.iter_mut() {
+            metric.first_commit = None;
+            metric.last_commit = None;
+            metric.age_days = None;
+        }
+    }
+
+    pub fn has_dates(&self) -> bool {
+        self.file_metrics.iter().any(|x| x.last_commit.is_some())
+    }
+
+    pub fn has_bugfixes(&self) -> bool {
+        self.file_metrics.iter().any(|x| x.bugfixes.is_some())
+    }
+
+    /// Tag every file with the package returned by `package_of`.
+    pub fn assign_packages(&mut self, package_of: impl Fn(&str) -> Option<String>) {
+        for metric in self.file_metrics.iter_mut() {
+            metric.package = package_of(&metric.filename);
+        }
+    }
+
+    /// Keep only the files of the given packages, keeping everything when `names` is empty.
+    pub fn retain_packages(&mut self, names: &[String]) {
+        if names.is_empty() {
+            return;
+        }
+        self.file_metrics.retain(|metric| {
+            metric
+                .package
+                .as_ref()
+                .map(|package| names.contains(package))
+                .unwrap_or(false)
+        });
+    }
+
+    /// Flag the files with uncommitted changes in the working tree.
+    pub fn mark_local_changes(&mut self, filenames: &[String]) {
+        for metric in self.file_metrics.iter_mut() {
+            metric.locally_modified = filenames.contains(&metric.filename);
+        }
+    }
+
+    /// Names of the references the churn is split by, empty unless several were analysed
+    pub fn ref_names(&self) -> Vec<String> {
+        let names: BTreeSet<_> = self
+            .file_metrics
+            .iter()
+            .flat_map(|metric| metric.churn_per_ref.keys())
+            .collect();
+        names.into_iter().cloned().collect()
+    }
+
+    pub fn has_packages(&self) -> bool {
+        self.file_metrics.iter().any(|x| x.package.is_some())
+    }
+
+    /// One summary per package, in name order, each holding its own `hotspot_count` top files.
+    pub fn package_summaries(&self, hotspot_count: usize) -> Vec<PackageSummary> {
+        let mut summaries: Vec<PackageSummary> = vec![];
+
+        for metric in &self.file_metrics {
+            let Some(name) = &metric.package else {
+                continue;
+            };
+            let summary = match summaries.iter_mut().position(|s| &s.name == name) {
+                Some(index) => &mut summaries[index],
+                None => {
+                    summaries.push(PackageSummary {
+                        name: name.clone(),
+                        file_count: 0,
+                        churn_sum: 0.0,
+                        complexity_sum: 0.0,
+                        hotspots: vec![],
+                    });
+                    summaries.last_mut().expect("summary just pushed")
+                }
+            };
+
+            summary.file_count += 1;
+            summary.churn_sum += metric.churn.as_f64();
+            summary.complexity_sum += metric.complexity;
+            summary.hotspots.push(metric.clone());
+        }
+
+        for summary in summaries.iter_mut() {
+            summary
+                .hotspots
+                .sort_by(|a, b| b.magnitude().total_cmp(&a.magnitude()));
+            summary.hotspots.truncate(hotspot_count);
+        }
+        summaries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        summaries
+    }
 }
 
 impl FileMetrics {
@@ -113,15 +877,72 @@ impl FileMetrics {
             filename,
             churn,
             complexity,
+            package: None,
+            functions: vec![],
+            coverage: None,
+            branch_coverage: None,
+            churn_per_ref: BTreeMap::new(),
+            locally_modified: false,
+            bugfixes: None,
+            lines: None,
+            first_commit: None,
+            last_commit: None,
+            age_days: None,
+            departed_share: None,
+            owners: vec![],
+            recent_commits: vec![],
+            monthly_churn: vec![],
+        }
+    }
+
+    /// File name, flagged when the file has uncommitted changes
+    pub fn display_name(&self) -> String {
+        if self.locally_modified {
+            format!("{} (modified)", self.filename)
+        } else {
+            self.filename.clone()
         }
     }
 
     pub fn magnitude(&self) -> f64 {
         let origin = (0.0, 0.0);
-        let distance_to_origin = ((origin.0 - self.churn.as_f64()).powi(2)
-            + (origin.1 - self.complexity as f64).powi(2))
-        .sqrt();
-        distance_to_origin
+        ((origin.0 - self.churn.as_f64()).powi(2) + (origin.1 - self.complexity).powi(2)).sqrt()
+    }
+
+    /// Magnitude where complexity is replaced by the highest CRAP score of the file, `None`
+    /// without coverage data
+    pub fn risk(&self) -> Option<f64> {
+        let crap = self.crap()?;
+        Some((self.churn.as_f64().powi(2) + crap.powi(2)).sqrt())
+    }
+
+    /// Highest CRAP score of the file functions, or of the whole file when functions are unknown
+    pub fn crap(&self) -> Option<f64> {
+        let functions = self.functions.iter().filter_map(FunctionMetrics::crap);
+        match functions.reduce(f64::max) {
+            Some(highest) => Some(highest),
+            None => self
+                .coverage
+                .map(|coverage| crap(self.complexity, coverage)),
+        }
+    }
+
+    /// Author of most of the current lines, with their share of the lines
+    pub fn main_owner(&self) -> Option<(&LineOwner, f64)> {
+        let total: usize = self.owners.iter().map(|owner| owner.lines).sum();
+        let owner = self.owners.first().filter(|_| total > 0)?;
+        Some((owner, owner.lines as f64 / total as f64))
+    }
+
+    /// Complexity weighted by the share of changes from departed authors, `None` without authors
+    pub fn knowledge_loss(&self) -> Option<f64> {
+        self.departed_share.map(|share| share * self.complexity)
+    }
+
+    /// Fix commits per thousand source lines of code, `None` without both
+    pub fn defect_density(&self) -> Option<f64> {
+        let lines = self.lines.filter(|lines| *lines > 0.0)?;
+        Some(self.bugfixes? as f64 * 1000.0 / lines)
     }
 
     pub fn to_point(&self) -> (f64, f64) {
@@ -151,7 +972,13 @@ mod tests {
 
     use crate::metrics::Churn;
 
-    use super::{metrics_per_file, FileMetrics, MetricReader, ProjectMetrics};
+    use crate::coverage::Coverage;
+    use crate::history::FileDates;
+
+    use super::{
+        crap, metrics_per_file, BlamedLine, FileMetrics, FunctionMetrics, LineOwner, MetricReader,
+        ProjectMetrics, Quadrant, SortKey,
+    };
 
     struct TestReader {}
 
@@ -164,21 +991,9 @@ mod tests {
     #[test]
     fn project_metric_compute() {
         let metric_data = vec![
-            FileMetrics {
-                churn: Churn::from(15),
-                complexity: 20.0,
-                filename: "foo.rs".to_string(),
-            },
-            FileMetrics {
-                churn: Churn::from(10),
-                complexity: 30.0,
-                filename: "foo.rs".to_string(),
-            },
-            FileMetrics {
-                churn: Churn::from(20),
-                complexity: 10.0,
-                filename: "foo.rs".to_string(),
-            },
+            FileMetrics::new("foo.rs".to_string(), Churn::from(15), 20.0),
+            FileMetrics::new("foo.rs".to_string(), Churn::from(10), 30.0),
+            FileMetrics::new("foo.rs".to_string(), Churn::from(20), 10.0),
         ];
 
         let metrics = ProjectMetrics::new(metric_data);
@@ -195,6 +1010,282 @@ mod tests {
         assert_eq!(sum_complexity, 60.0);
     }
 
+    #[test]
+    fn quadrants_split_on_means() {
+        let metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("hot.rs".to_string(), Churn::from(10), 10.0),
+            FileMetrics::new("complex.rs".to_string(), Churn::from(1), 10.0),
+            FileMetrics::new("churning.rs".to_string(), Churn::from(10), 1.0),
+            FileMetrics::new("healthy.rs".to_string(), Churn::from(1), 1.0),
+        ]);
+
+        let quadrants: Vec<_> = metrics
+            .file_metrics()
+            .iter()
+            .map(|metric| metrics.quadrant_of(metric))
+            .collect();
+
+        assert_eq!(quadrants, Quadrant::ALL.to_vec());
+        assert!(metrics
+            .quadrant_counts()
+            .iter()
+            .all(|(_, count)| *count == 1));
+    }
+
+    #[test]
+    fn changes_against_baseline() {
+        let baseline = ProjectMetrics::new(vec![
+            FileMetrics::new("same.rs".to_string(), Churn::from(1), 1.0),
+            FileMetrics::new("worse.rs".to_string(), Churn::from(1), 1.0),
+            FileMetrics::new("removed.rs".to_string(), Churn::from(1), 1.0),
+        ]);
+        let current = ProjectMetrics::new(vec![
+            FileMetrics::new("same.rs".to_string(), Churn::from(1), 1.0),
+            FileMetrics::new("worse.rs".to_string(), Churn::from(2), 5.0),
+            FileMetrics::new("added.rs".to_string(), Churn::from(1), 1.0),
+        ]);
+
+        let changes = current.changes_since(&baseline);
+        let names: Vec<_> = changes.iter().map(|c| c.filename.as_str()).collect();
+
+        assert_eq!(names, vec!["worse.rs", "added.rs", "removed.rs"]);
+        assert!(changes[1].before.is_none());
+        assert!(changes[2].after.is_none());
+    }
+
+    #[test]
+    fn untested_files_are_riskier() {
+        let mut tested = FileMetrics::new("tested.rs".to_string(), Churn::from(3), 10.0);
+        tested.coverage = Some(1.0);
+        let mut untested = FileMetrics::new("untested.rs".to_string(), Churn::from(3), 10.0);
+        untested.coverage = Some(0.0);
+        let unknown = FileMetrics::new("unknown.rs".to_string(), Churn::from(30), 30.0);
+
+        assert_eq!(crap(10.0, 1.0), 10.0);
+        assert_eq!(crap(10.0, 0.0), 110.0);
+
+        let mut metrics = ProjectMetrics::new(vec![unknown, tested, untested]);
+        metrics.sort_by(SortKey::Risk);
+        let names: Vec<_> = metrics
+            .file_metrics()
+            .iter()
+            .map(|m| m.filename.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["untested.rs", "tested.rs", "unknown.rs"]);
+    }
+
+    #[test]
+    fn age_since_last_change() {
+        let day = 24 * 60 * 60;
+        let mut metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("old.rs".to_string(), Churn::from(2), 1.0),
+            FileMetrics::new("new.rs".to_string(), Churn::from(1), 1.0),
+            FileMetrics::new("untracked.rs".to_string(), Churn::from(1), 1.0),
+        ]);
+
+        metrics.assign_dates(
+            &HashMap::from([
+                (
+                    "old.rs".to_string(),
+                    FileDates {
+                        first: 0,
+                        last: 10 * day,
+                    },
+                ),
+                (
+                    "new.rs".to_string(),
+                    FileDates {
+                        first: 99 * day,
+                        last: 100 * day,
+                    },
+                ),
+            ]),
+            100 * day + 1,
+        );
+        metrics.sort_by(SortKey::Recency);
+
+        let ages: Vec<_> = metrics.file_metrics().iter().map(|m| m.age_days).collect();
+        assert_eq!(ages, vec![Some(0), Some(90), None]);
+        assert_eq!(metrics.file_metrics()[1].first_commit, Some(0));
+
+        metrics.clear_dates();
+        assert!(!metrics.has_dates());
+        assert_eq!(metrics.file_metrics()[0].filename, "new.rs");
+    }
+
+    #[test]
+    fn complex_abandoned_files_first() {
+        let mut metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("simple.rs".to_string(), Churn::from(1), 2.0),
+            FileMetrics::new("complex.rs".to_string(), Churn::from(1), 20.0),
+            FileMetrics::new("shared.rs".to_string(), Churn::from(1), 50.0),
+        ]);
+
+        metrics.assign_departed_shares(&HashMap::from([
+            ("simple.rs".to_string(), 1.0),
+            ("complex.rs".to_string(), 0.75),
+            ("shared.rs".to_string(), 0.5),
+        ]));
+
+        let names: Vec<_> = metrics
+            .files_by_knowledge_loss()
+            .iter()
+            .map(|m| m.filename.as_str())
+            .collect();
+        assert_eq!(names, vec!["complex.rs", "simple.rs"]);
+        assert_eq!(
+            metrics.get("complex.rs").and_then(|m| m.knowledge_loss()),
+            Some(15.0)
+        );
+    }
+
+    #[test]
+    fn owners_per_directory() {
+        let blamed = |author: &str, lines| {
+            vec![
+                BlamedLine {
+                    commit: format!("{author} commit"),
+                    author: author.to_string(),
+                    email: format!("{author}@example.com"),
+                };
+                lines
+            ]
+        };
+        let mut metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("src/a.rs".to_string(), Churn::from(1), 1.0),
+            FileMetrics::new("src/b.rs".to_string(), Churn::from(1), 1.0),
+            FileMetrics::new("build.rs".to_string(), Churn::from(1), 1.0),
+        ]);
+
+        metrics.load_blame_with(|filename| match filename {
+            "src/a.rs" => [blamed("alice", 30), blamed("bob", 10)].concat(),
+            "src/b.rs" => blamed("bob", 25),
+            _ => vec![],
+        });
+
+        let (main_owner, share) = metrics
+            .get("src/a.rs")
+            .and_then(|m| m.main_owner())
+            .expect("owner");
+        assert_eq!((main_owner.author.as_str(), share), ("alice", 0.75));
+        let directories = metrics.directory_owners();
+        assert_eq!(directories.len(), 1);
+        assert_eq!(directories[0].directory, "src");
+        assert_eq!(
+            directories[0].owners,
+            vec![LineOwner::new("bob", 35), LineOwner::new("alice", 30)]
+        );
+        assert_eq!(directories[0].total_lines(), 65);
+    }
+
+    #[test]
+    fn function_commits_from_blame() {
+        let mut file = FileMetrics::new("foo.rs".to_string(), Churn::from(3), 2.0);
+        file.functions = vec![
+            FunctionMetrics::new("a", 1, 2, 1.0),
+            FunctionMetrics::new("b", 3, 5, 1.0),
+            FunctionMetrics::new("c", 9, 9, 1.0),
+        ];
+        let mut metrics = ProjectMetrics::new(vec![file]);
+
+        metrics.load_blame_with(|_| {
+            ["1", "1", "2", "3", "2"]
+                .into_iter()
+                .map(|commit| BlamedLine {
+                    commit: commit.to_string(),
+                    author: "alice".to_string(),
+                    email: String::new(),
+                })
+                .collect()
+        });
+
+        let commits: Vec<_> = metrics.file_metrics()[0]
+            .functions
+            .iter()
+            .map(|function| function.blame_commits)
+            .collect();
+        assert_eq!(commits, vec![Some(1), Some(2), None]);
+        assert_eq!(metrics.file_metrics()[0].owners[0].lines, 5);
+    }
+
+    #[test]
+    fn explain_a_file() {
+        let mut hotspot = FileMetrics::new("hot.rs".to_string(), Churn::from(10), 30.0);
+        hotspot.functions = [(1.0, "a"), (20.0, "b"), (9.0, "c")]
+            .into_iter()
+            .map(|(cyclomatic, name)| FunctionMetrics::new(name, 1, 2, cyclomatic))
+            .collect();
+        let metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("cold.rs".to_string(), Churn::from(1), 2.0),
+            hotspot,
+            FileMetrics::new("warm.rs".to_string(), Churn::from(2), 5.0),
+            FileMetrics::new("mild.rs".to_string(), Churn::from(1), 4.0),
+        ]);
+
+        let explanation = metrics.explain("hot.rs", 2).expect("explanation");
+        assert_eq!((explanation.rank, explanation.file_count), (1, 4));
+        assert_eq!(explanation.percentile, 1.0);
+        assert_eq!(explanation.quadrant, Quadrant::Hotspot);
+        let names: Vec<_> = explanation
+            .top_functions
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["b", "c"]);
+
+        let explanation = metrics.explain("mild.rs", 2).expect("explanation");
+        assert_eq!((explanation.rank, explanation.percentile), (3, 0.5));
+        assert!(metrics.explain("unknown.rs", 2).is_none());
+    }
+
+    #[test]
+    fn sort_by_defect_density() {
+        let mut small = FileMetrics::new("small.rs".to_string(), Churn::from(2), 1.0);
+        small.lines = Some(100.0);
+        let mut large = FileMetrics::new("large.rs".to_string(), Churn::from(9), 1.0);
+        large.lines = Some(2000.0);
+        let mut metrics = ProjectMetrics::new(vec![large, small]);
+
+        metrics.assign_bugfixes(&HashMap::from([
+            ("small.rs".to_string(), 1),
+            ("large.rs".to_string(), 4),
+        ]));
+        metrics.sort_by(SortKey::DefectDensity);
+
+        let small = &metrics.file_metrics()[0];
+        assert_eq!(small.filename, "small.rs");
+        assert_eq!(small.defect_density(), Some(10.0));
+        assert_eq!(metrics.file_metrics()[1].defect_density(), Some(2.0));
+    }
+
+    #[test]
+    fn crap_per_function() {
+        let mut file = FileMetrics::new("src/lib.rs".to_string(), Churn::from(1), 12.0);
+        file.functions = vec![
+            FunctionMetrics::new("tested", 1, 2, 10.0),
+            FunctionMetrics::new("untested", 3, 4, 2.0),
+        ];
+        let mut metrics = ProjectMetrics::new(vec![file]);
+        let coverage = Coverage::from_lcov("SF:src/lib.rs\nDA:1,1\nDA:2,1\nDA:3,0\nDA:4,0\n")
+            .expect("lcov parsed");
+
+        metrics.apply_coverage(&coverage);
+
+        let ranked: Vec<_> = metrics
+            .functions_by_crap()
+            .iter()
+            .map(|(_, function)| (function.name.as_str(), function.crap()))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![("tested", Some(10.0)), ("untested", Some(6.0))]
+        );
+        assert_eq!(metrics.file_metrics()[0].crap(), Some(10.0));
+        // The riskiest function counts, not the complexity summed over the file
+        assert_eq!(metrics.file_metrics()[0].risk(), Some(101.0_f64.sqrt()));
+    }
+
     #[test]
     fn magnitude() {
         let metric = FileMetrics::new("foo.rs".to_string(), Churn::from(2), 2.0);
@@ -213,6 +1304,50 @@ mod tests {
         assert!(results[0].complexity == 1.0);
     }
 
+    #[test]
+    fn summaries_per_package() {
+        let mut metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("core/src/lib.rs".to_string(), Churn::from(10), 10.0),
+            FileMetrics::new("core/src/parse.rs".to_string(), Churn::from(2), 30.0),
+            FileMetrics::new("cli/src/main.rs".to_string(), Churn::from(5), 5.0),
+            FileMetrics::new("README.md".to_string(), Churn::from(5), 1.0),
+        ]);
+        metrics.assign_packages(|filename| {
+            filename
+                .split_once('/')
+                .map(|(package, _)| package.to_string())
+        });
+
+        let summaries = metrics.package_summaries(1);
+
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].name, "cli");
+        assert_eq!(summaries[1].name, "core");
+        assert_eq!(summaries[1].file_count, 2);
+        assert_eq!(summaries[1].churn_sum, 12.0);
+        assert_eq!(summaries[1].hotspots[0].filename, "core/src/parse.rs");
+
+        metrics.retain_packages(&["cli".to_string()]);
+        assert_eq!(metrics.file_metrics().len(), 1);
+    }
+
+    #[test]
+    fn mark_local_changes() {
+        let mut metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("clean.rs".to_string(), Churn::from(1), 1.0),
+            FileMetrics::new("edited.rs".to_string(), Churn::from(2), 1.0),
+        ]);
+
+        metrics.mark_local_changes(&["edited.rs".to_string()]);
+
+        let names: Vec<_> = metrics
+            .file_metrics()
+            .iter()
+            .map(FileMetrics::display_name)
+            .collect();
+        assert_eq!(names, vec!["clean.rs", "edited.rs (modified)"]);
+    }
+
     mod integration {
         use std::fs::File;
         use std::io::Write;
@@ -245,6 +1380,12 @@ mod tests {
             let metric = reader.get_cyclomatic_from_path_and_content(file_path.as_path());
 
             metric.expect("cyclomatic complexity");
+
+            let functions = reader.get_functions_from_path(file_path.as_path());
+            assert_eq!(functions.len(), 1);
+            assert_eq!(functions[0].name, "f");
+            assert_eq!(functions[0].start_line, 1);
+            assert!(functions[0].cyclomatic > 1.0);
         }
     }
 }
@@ -1,23 +1,81 @@
 use tui::{
+    layout::Rect,
     style::{Color, Modifier, Style},
     text::Span,
     widgets::{Axis, Block, Borders, Chart, Dataset},
 };
 
-pub fn complexity_churn_threshold(
-    churn: f64,
-    complexity_threshold: f64,
-    churn_threshold: f64,
-) -> f64 {
-    (100f64 / churn - churn_threshold) + complexity_threshold
+use crate::output::scale::AxisScale;
+
+/// Farthest a click can be from a point to select it, in lines
+const MAXIMUM_CLICK_DISTANCE: f64 = 3.0;
+
+/// Ticks wanted on each axis, linear scales giving between this and twice as many
+const TICK_COUNT: usize = 5;
+
+/// Room left above the highest value, so that its point stays clear of the axis title
+const HEADROOM: f64 = 1.1;
+
+/// Ticks of a chart axis
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisTicks {
+    scale: AxisScale,
+    ticks: Vec<f64>,
 }
 
-pub fn create_chart(datasets: Vec<Dataset>, maximum_churn: f64, maximum_complexity: f64) -> Chart {
+impl AxisTicks {
+    /// Round ticks from zero, or one on logarithmic scales, up to above `maximum`
+    pub fn new(scale: AxisScale, maximum: f64) -> Self {
+        Self {
+            scale,
+            ticks: scale.ticks(maximum.max(1.0) * HEADROOM, TICK_COUNT),
+        }
+    }
+
+    /// Where `value` is drawn along the axis
+    pub fn position(&self, value: f64) -> f64 {
+        self.scale.position(value)
+    }
+
+    fn bounds(&self) -> [f64; 2] {
+        let first = self.ticks.first().copied().unwrap_or_default();
+        let last = self.ticks.last().copied().unwrap_or_default();
+        [self.position(first), self.position(last)]
+    }
+
+    fn labels(&self) -> Vec<String> {
+        self.ticks.iter().map(|tick| format_value(*tick)).collect()
+    }
+
+    fn title(&self, title: &str) -> String {
+        match self.scale {
+            AxisScale::Linear => title.to_string(),
+            AxisScale::Logarithmic => format!("{title} (log)"),
+        }
+    }
+}
+
+/// Value rounded to three decimals, to hide the floating point noise of the ticks
+fn format_value(value: f64) -> String {
+    ((value * 1000.0).round() / 1000.0).to_string()
+}
+
+/// Name of a file without its directories, to label points
+pub fn short_name(filename: &str) -> &str {
+    filename.rsplit('/').next().unwrap_or(filename)
+}
+
+pub fn create_chart<'a>(
+    datasets: Vec<Dataset<'a>>,
+    x_axis: &AxisTicks,
+    y_axis: &AxisTicks,
+    y_title: &'static str,
+) -> Chart<'a> {
     Chart::new(datasets)
         .block(
             Block::default()
                 .title(Span::styled(
-                    "Churn vs Complexity",
+                    format!("Churn vs {y_title}"),
                     Style::default()
                         .fg(Color::Cyan)
                         .add_modifier(Modifier::BOLD),
@@ -26,42 +84,250 @@ pub fn create_chart(datasets: Vec<Dataset>, maximum_churn: f64, maximum_complexi
         )
         .x_axis(
             Axis::default()
-                .title("Churn")
+                .title(x_axis.title("Churn"))
                 .style(Style::default().fg(Color::Gray))
-                .bounds([0.0, maximum_churn])
-                .labels(vec![
-                    Span::styled("0", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw((maximum_churn.div_euclid(2.0)).to_string()),
-                    Span::styled(
-                        maximum_churn.to_string(),
-                        Style::default().add_modifier(Modifier::BOLD),
-                    ),
-                ]),
+                .bounds(x_axis.bounds())
+                .labels(label_spans(x_axis.labels())),
         )
         .y_axis(
             Axis::default()
-                .title("Complexity")
+                .title(y_axis.title(y_title))
                 .style(Style::default().fg(Color::Gray))
-                .bounds([0.0, maximum_complexity])
-                .labels(vec![
-                    Span::styled("0", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw((maximum_complexity.div_euclid(2.0)).to_string()),
-                    Span::styled(
-                        maximum_complexity.to_string(),
-                        Style::default().add_modifier(Modifier::BOLD),
-                    ),
-                ]),
+                .bounds(y_axis.bounds())
+                .labels(label_spans(y_axis.labels())),
         )
 }
 
+/// Labels of the ticks, the bounds in bold
+fn label_spans(labels: Vec<String>) -> Vec<Span<'static>> {
+    let last = labels.len().saturating_sub(1);
+    labels
+        .into_iter()
+        .enumerate()
+        .map(|(index, label)| {
+            if index == 0 || index == last {
+                Span::styled(label, Style::default().add_modifier(Modifier::BOLD))
+            } else {
+                Span::raw(label)
+            }
+        })
+        .collect()
+}
+
+/// Cells where a chart from [`create_chart`] draws its points, to find them back on screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotArea {
+    area: Rect,
+    /// Left of the labels of the vertical axis
+    labels_left: u16,
+    x_axis: AxisTicks,
+    y_axis: AxisTicks,
+}
+
+impl PlotArea {
+    /// Mirror the layout of the chart rendered in `chart_area`: inside its borders, right of
+    /// the vertical axis and its labels, above the horizontal axis and its labels
+    pub fn new(chart_area: Rect, x_axis: AxisTicks, y_axis: AxisTicks) -> Self {
+        let inner = Block::default().borders(Borders::ALL).inner(chart_area);
+        // The first horizontal label ends below the vertical axis
+        let first_x_label = x_axis.labels().first().map_or(0, |label| label.len() - 1);
+        let label_width = y_axis
+            .labels()
+            .iter()
+            .map(String::len)
+            .chain([first_x_label])
+            .max()
+            .unwrap_or_default()
+            .min(usize::from(inner.width / 3)) as u16;
+        let left = inner.left() + label_width + 1;
+        let bottom = inner.bottom().saturating_sub(2);
+        let area = Rect::new(
+            left,
+            inner.top(),
+            inner.right().saturating_sub(left),
+            bottom.saturating_sub(inner.top()),
+        );
+        Self {
+            area,
+            labels_left: inner.left(),
+            x_axis,
+            y_axis,
+        }
+    }
+
+    /// Cell of the point `(x, y)`, `None` when it is out of the bounds
+    pub fn position(&self, (x, y): (f64, f64)) -> Option<(u16, u16)> {
+        let [left, right] = self.x_axis.bounds();
+        let [bottom, top] = self.y_axis.bounds();
+        let (x, y) = (self.x_axis.position(x), self.y_axis.position(y));
+        if self.area.area() == 0 || !(left..=right).contains(&x) || !(bottom..=top).contains(&y) {
+            return None;
+        }
+        let column = (x - left) / (right - left).max(f64::EPSILON) * f64::from(self.area.width - 1);
+        let row = (top - y) / (top - bottom).max(f64::EPSILON) * f64::from(self.area.height - 1);
+        Some((
+            self.area.left() + column as u16,
+            self.area.top() + row as u16,
+        ))
+    }
+
+    /// Marks on both axes at the coordinates of the point, with their values
+    pub fn axis_marks(&self, point: (f64, f64)) -> Vec<(Rect, String)> {
+        let Some((column, row)) = self.position(point) else {
+            return vec![];
+        };
+        let axis_row = self.area.bottom();
+        let axis_column = self.area.left() - 1;
+        let x_value = format_value(point.0);
+        let x_width = (x_value.len() as u16).min(self.area.width);
+        let y_value = format_value(point.1);
+        let y_width = (y_value.len() as u16).min(axis_column - self.labels_left);
+        vec![
+            (Rect::new(column, axis_row, 1, 1), "▲".to_string()),
+            (
+                Rect::new(
+                    column.min(self.area.right() - x_width),
+                    axis_row + 1,
+                    x_width,
+                    1,
+                ),
+                x_value,
+            ),
+            (Rect::new(axis_column, row, 1, 1), "▶".to_string()),
+            (Rect::new(axis_column - y_width, row, y_width, 1), y_value),
+        ]
+    }
+
+    /// Cells of labels `width` columns wide beside each point, by decreasing priority. Labels
+    /// overlapping one already placed are left out
+    pub fn place_labels(&self, labels: &[((f64, f64), u16)]) -> Vec<Option<Rect>> {
+        let mut placed: Vec<Rect> = vec![];
+        labels
+            .iter()
+            .map(|(point, width)| {
+                let area = self
+                    .tooltip(*point, *width)
+                    .filter(|area| !placed.iter().any(|other| other.intersects(*area)))?;
+                placed.push(area);
+                Some(area)
+            })
+            .collect()
+    }
+
+    /// Cells of a tooltip `width` columns wide beside the point, on its left when there is
+    /// no room on its right
+    pub fn tooltip(&self, point: (f64, f64), width: u16) -> Option<Rect> {
+        let (column, row) = self.position(point)?;
+        let width = width.min(self.area.width);
+        let left = if column + 2 + width <= self.area.right() {
+            column + 2
+        } else {
+            column.saturating_sub(width + 1).max(self.area.left())
+        };
+        Some(Rect::new(left, row, width, 1))
+    }
+
+    /// Index of the point nearest the cell `(column, row)`, when close enough to be clicked.
+    /// Cells being about twice as high as wide, columns count for half a line
+    pub fn nearest(
+        &self,
+        points: impl IntoIterator<Item = (usize, (f64, f64))>,
+        column: u16,
+        row: u16,
+    ) -> Option<usize> {
+        points
+            .into_iter()
+            .filter_map(|(index, point)| {
+                let (x, y) = self.position(point)?;
+                let dx = (f64::from(x) - f64::from(column)) / 2.0;
+                let dy = f64::from(y) - f64::from(row);
+                Some((index, dx.hypot(dy)))
+            })
+            .filter(|(_, distance)| *distance <= MAXIMUM_CLICK_DISTANCE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::output::app::chart::complexity_churn_threshold;
+    use super::*;
+
+    fn linear_plot() -> PlotArea {
+        let x_axis = AxisTicks::new(AxisScale::Linear, 18.0);
+        let y_axis = AxisTicks::new(AxisScale::Linear, 90.0);
+        PlotArea::new(Rect::new(0, 0, 46, 23), x_axis, y_axis)
+    }
+
+    #[test]
+    fn round_ticks() {
+        let axis = AxisTicks::new(AxisScale::Linear, 17.0);
+        assert_eq!(axis.labels(), vec!["0", "5", "10", "15", "20"]);
+        assert_eq!(axis.bounds(), [0.0, 20.0]);
+        assert_eq!(
+            AxisTicks::new(AxisScale::Linear, 0.0).labels(),
+            vec!["0", "0.5", "1", "1.5"]
+        );
+
+        let axis = AxisTicks::new(AxisScale::Logarithmic, 150.0);
+        assert_eq!(axis.labels(), vec!["1", "10", "100", "1000"]);
+        assert_eq!(axis.bounds(), [0.0, 3.0]);
+        assert_eq!(axis.title("Churn"), "Churn (log)");
+    }
 
     #[test]
-    fn threshold() {
-        let result = complexity_churn_threshold(2.0, 10.0, 10.0);
-        assert!(result > 0.0);
-        assert!(result < 100.0);
+    fn locate_points_on_screen() {
+        // Borders, then labels as wide as "100" and the vertical axis, then two lines of
+        // horizontal axis and labels
+        let plot = linear_plot();
+        assert_eq!(plot.area, Rect::new(5, 1, 40, 19));
+
+        assert_eq!(plot.position((0.0, 0.0)), Some((5, 19)));
+        assert_eq!(plot.position((20.0, 100.0)), Some((44, 1)));
+        assert_eq!(plot.position((30.0, 0.0)), None);
+
+        let points = [(0, (0.0, 0.0)), (1, (10.0, 50.0)), (2, (20.0, 100.0))];
+        assert_eq!(plot.nearest(points, 26, 9), Some(1));
+        assert_eq!(plot.nearest(points, 42, 2), Some(2));
+        assert_eq!(plot.nearest(points, 5, 5), None);
+
+        assert_eq!(plot.tooltip((10.0, 50.0), 6), Some(Rect::new(26, 10, 6, 1)));
+        assert_eq!(plot.tooltip((20.0, 100.0), 6), Some(Rect::new(37, 1, 6, 1)));
+        assert_eq!(plot.tooltip((0.0, 0.0), 60), Some(Rect::new(5, 19, 40, 1)));
+    }
+
+    #[test]
+    fn logarithmic_positions() {
+        let x_axis = AxisTicks::new(AxisScale::Logarithmic, 90.0);
+        let y_axis = AxisTicks::new(AxisScale::Logarithmic, 90.0);
+        let plot = PlotArea::new(Rect::new(0, 0, 46, 23), x_axis, y_axis);
+
+        // Ten is halfway between one and a hundred
+        assert_eq!(plot.position((1.0, 0.0)), Some((5, 19)));
+        assert_eq!(plot.position((10.0, 10.0)), Some((24, 10)));
+    }
+
+    #[test]
+    fn mark_the_axes_and_label_points() {
+        let plot = linear_plot();
+
+        assert_eq!(
+            plot.axis_marks((10.0, 50.0)),
+            vec![
+                (Rect::new(24, 20, 1, 1), "▲".to_string()),
+                (Rect::new(24, 21, 2, 1), "10".to_string()),
+                (Rect::new(4, 10, 1, 1), "▶".to_string()),
+                (Rect::new(2, 10, 2, 1), "50".to_string()),
+            ]
+        );
+        assert_eq!(
+            plot.place_labels(&[((10.0, 50.0), 4), ((11.0, 50.0), 4), ((0.0, 0.0), 4)]),
+            vec![
+                Some(Rect::new(26, 10, 4, 1)),
+                None,
+                Some(Rect::new(7, 19, 4, 1)),
+            ]
+        );
+        assert_eq!(short_name("src/output/mod.rs"), "mod.rs");
     }
 }
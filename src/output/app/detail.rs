@@ -0,0 +1,95 @@
+use tui::{
+    style::{Color, Style},
+    text::Spans,
+    widgets::{Block, Borders, Paragraph, Sparkline, Wrap},
+};
+
+use crate::metrics::{Explanation, FileMetrics};
+use crate::output::explain::explanation_lines;
+
+/// Details of the selected file, or a hint when none is selected
+pub fn detail_pane(explanation: Option<&Explanation>) -> Paragraph<'static> {
+    let lines = match explanation {
+        Some(explanation) => explanation_lines(explanation)
+            .into_iter()
+            .map(Spans::from)
+            .collect(),
+        None => vec![Spans::from("Select a file to see its details")],
+    };
+
+    Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Details (Enter to close)"),
+        )
+        .wrap(Wrap { trim: false })
+}
+
+/// Commits changing the file in each of the last months
+pub fn churn_sparkline(metric: Option<&FileMetrics>) -> Sparkline<'_> {
+    let data = metric.map(|metric| metric.monthly_churn.as_slice());
+    Sparkline::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Churn per month"),
+        )
+        .style(Style::default().fg(Color::Yellow))
+        .data(data.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use tui::{backend::TestBackend, layout::Rect, Terminal};
+
+    use crate::{metrics::ProjectMetrics, Churn};
+
+    use super::*;
+
+    fn screen_text(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area().width as usize)
+            .map(|line| {
+                line.iter()
+                    .map(|cell| cell.symbol.as_str())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn render_detail_widgets() {
+        let mut metric = FileMetrics::new("foo.rs".to_string(), Churn::from(1), 1.0);
+        metric.monthly_churn = vec![0, 1, 3];
+        let metrics = ProjectMetrics::new(vec![metric.clone()]);
+        let mut terminal = Terminal::new(TestBackend::new(60, 16)).expect("terminal");
+
+        terminal
+            .draw(|f| {
+                let pane = detail_pane(metrics.explain("foo.rs", 3).as_ref());
+                f.render_widget(pane, Rect::new(0, 0, 60, 11));
+                f.render_widget(churn_sparkline(Some(&metric)), Rect::new(0, 11, 60, 5));
+            })
+            .expect("drawn");
+
+        let text = screen_text(&terminal);
+        assert!(text.contains("Rank 1 of 1, at or above 100% of the files"));
+        assert!(text.contains("Churn per month"));
+        let bars: Vec<_> = text
+            .lines()
+            .nth(14)
+            .expect("last bar row")
+            .chars()
+            .collect();
+        assert_eq!(&bars[1..4], &[' ', '█', '█']);
+
+        terminal
+            .draw(|f| f.render_widget(detail_pane(None), Rect::new(0, 0, 60, 11)))
+            .expect("drawn");
+        assert!(screen_text(&terminal).contains("Select a file to see its details"));
+    }
+}
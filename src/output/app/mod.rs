@@ -1,45 +1,381 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::{io, vec};
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     symbols,
-    widgets::{Cell, Dataset, GraphType, TableState},
+    widgets::{Cell, Dataset, GraphType, Paragraph, TableState},
     Frame, Terminal,
 };
 
 use crate::metrics::{FileMetrics, ProjectMetrics};
 
 use self::{
-    chart::{complexity_churn_threshold, create_chart},
-    table::file_table,
+    chart::{create_chart, short_name, AxisTicks, PlotArea},
+    detail::{churn_sparkline, detail_pane},
+    rows::{parse_conditions, Column, RowFilter},
+    source::{SourceView, PAGE_LINES},
+    table::{file_table, row_at, scroll_offset},
 };
 
+use super::scale::{threshold_points, AxisScale};
+
 mod chart;
+mod detail;
+mod rows;
+mod source;
 mod table;
 
+/// Number of functions listed in the details of the selected file
+const DETAIL_FUNCTION_COUNT: usize = 3;
+
+/// Rows moved by page up and page down
+const PAGE_ROWS: usize = 10;
+
+/// Number of hotspots named on the chart, besides the selected file
+const HOTSPOT_LABEL_COUNT: usize = 5;
+
+/// Source lines scrolled by each turn of the mouse wheel
+const WHEEL_LINES: u16 = 3;
+
+/// Metric drawn on the vertical axis of the chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YAxisMetric {
+    Complexity,
+    Crap,
+}
+
+impl YAxisMetric {
+    fn title(self) -> &'static str {
+        match self {
+            YAxisMetric::Complexity => "Complexity",
+            YAxisMetric::Crap => "CRAP",
+        }
+    }
+
+    fn value(self, metric: &FileMetrics) -> Option<f64> {
+        match self {
+            YAxisMetric::Complexity => Some(metric.complexity),
+            YAxisMetric::Crap => metric.crap(),
+        }
+    }
+}
+
+/// Text typed at the bottom of the screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Prompt {
+    /// Fuzzy search over the filenames, applied while typing
+    Search,
+    /// Conditions such as `complexity>20`, applied on `Enter`
+    Filter(String),
+}
+
 pub struct App {
     state: TableState,
     metrics: ProjectMetrics,
+    y_metric: YAxisMetric,
+    scale: AxisScale,
+    show_details: bool,
+    /// Source of the selected file, shown in place of the chart
+    source: Option<SourceView>,
+    /// Whether the sources can be read from the working directory
+    sources_on_disk: bool,
+    filter: RowFilter,
+    /// Positions of the shown files in `metrics`, in table order
+    rows: Vec<usize>,
+    prompt: Option<Prompt>,
+    /// Last filter applied, to edit it again
+    filter_text: String,
+    /// Why the filter typed was rejected
+    error: Option<String>,
+    /// Where the table was last drawn, and its first row shown, to find the clicked rows
+    table_area: Rect,
+    table_offset: usize,
+    /// Where the chart points were last drawn, `None` while the source is shown
+    plot: Option<PlotArea>,
 }
 
 impl App {
     fn new(metrics: ProjectMetrics) -> Self {
+        let rows = (0..metrics.file_metrics().len()).collect();
         App {
             state: TableState::default(),
             metrics,
+            y_metric: YAxisMetric::Complexity,
+            scale: AxisScale::Linear,
+            show_details: false,
+            source: None,
+            sources_on_disk: true,
+            filter: RowFilter::default(),
+            rows,
+            prompt: None,
+            filter_text: String::new(),
+            error: None,
+            table_area: Rect::default(),
+            table_offset: 0,
+            plot: None,
+        }
+    }
+
+    fn selected_metric(&self) -> Option<&FileMetrics> {
+        self.state
+            .selected()
+            .and_then(|row| self.rows.get(row))
+            .and_then(|index| self.metrics.file_metrics().get(*index))
+    }
+
+    /// Apply a change of the sort, search or filter, keeping the selected file when still shown
+    fn refresh_rows(&mut self) {
+        let selected = self
+            .state
+            .selected()
+            .and_then(|row| self.rows.get(row))
+            .copied();
+        self.rows = self.filter.rows(self.metrics.file_metrics());
+        let row = match selected {
+            Some(index) => self
+                .rows
+                .iter()
+                .position(|shown| *shown == index)
+                .or(Some(0)),
+            None => None,
+        };
+        self.state.select(row.filter(|_| !self.rows.is_empty()));
+    }
+
+    /// Handle a key press, returning whether the app keeps running
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        match self.prompt.take() {
+            Some(Prompt::Search) => self.search_key(code),
+            Some(Prompt::Filter(text)) => self.filter_key(code, text),
+            None if self.source.is_some() => return self.source_key(code),
+            None => return self.table_key(code),
+        }
+        true
+    }
+
+    fn table_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Down => self.next(),
+            KeyCode::Up => self.previous(),
+            KeyCode::PageDown => self.move_by(PAGE_ROWS as isize),
+            KeyCode::PageUp => self.move_by(-(PAGE_ROWS as isize)),
+            KeyCode::Home => self.first(),
+            KeyCode::End => self.last(),
+            KeyCode::Char('m') => self.next_y_metric(),
+            KeyCode::Char('l') => self.toggle_log_scale(),
+            KeyCode::Char('s') => self.toggle_source(),
+            KeyCode::Enter => self.toggle_details(),
+            KeyCode::Char('/') => self.prompt = Some(Prompt::Search),
+            KeyCode::Char('f') => self.prompt = Some(Prompt::Filter(self.filter_text.clone())),
+            KeyCode::Char(c @ '1'..='4') => {
+                let column = Column::ALL[c as usize - '1' as usize];
+                self.filter.sort_by(column);
+                self.refresh_rows();
+            }
+            KeyCode::Esc => {
+                self.filter.search.clear();
+                self.filter.conditions.clear();
+                self.filter_text.clear();
+                self.refresh_rows();
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Select the clicked row or chart point, and scroll the table or the source with the wheel
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        let (column, line) = (event.column, event.row);
+        let over_table = row_at(self.table_area, 0, usize::MAX, column, line).is_some();
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) if over_table => {
+                let clicked = row_at(
+                    self.table_area,
+                    self.table_offset,
+                    self.rows.len(),
+                    column,
+                    line,
+                );
+                if let Some(row) = clicked {
+                    self.select_row(row);
+                }
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                let points = self.rows.iter().enumerate().filter_map(|(row, index)| {
+                    self.point(&self.metrics.file_metrics()[*index])
+                        .map(|point| (row, point))
+                });
+                let clicked = self
+                    .plot
+                    .as_ref()
+                    .and_then(|plot| plot.nearest(points, column, line));
+                if let Some(row) = clicked {
+                    self.select_row(row);
+                }
+            }
+            MouseEventKind::ScrollDown if over_table => self.move_by(1),
+            MouseEventKind::ScrollUp if over_table => self.move_by(-1),
+            MouseEventKind::ScrollDown => {
+                if let Some(source) = self.source.as_mut() {
+                    source.scroll_down(WHEEL_LINES);
+                }
+            }
+            MouseEventKind::ScrollUp => {
+                if let Some(source) = self.source.as_mut() {
+                    source.scroll_up(WHEEL_LINES);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Select the file of `row`, showing its source instead when the source is open
+    fn select_row(&mut self, row: usize) {
+        self.state.select(Some(row));
+        if self.source.is_some() {
+            self.source = self.selected_metric().map(SourceView::open);
+        }
+    }
+
+    fn source_key(&mut self, code: KeyCode) -> bool {
+        if let Some(source) = self.source.as_mut() {
+            match code {
+                KeyCode::Char('q') => return false,
+                KeyCode::Down => source.scroll_down(1),
+                KeyCode::Up => source.scroll_up(1),
+                KeyCode::PageDown => source.scroll_down(PAGE_LINES),
+                KeyCode::PageUp => source.scroll_up(PAGE_LINES),
+                KeyCode::Char('s') | KeyCode::Esc => self.source = None,
+                _ => {}
+            }
+        }
+        true
+    }
+
+    fn search_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c) => self.filter.search.push(c),
+            KeyCode::Backspace => {
+                self.filter.search.pop();
+            }
+            KeyCode::Enter => return,
+            KeyCode::Esc => self.filter.search.clear(),
+            _ => {}
         }
+        if code != KeyCode::Esc {
+            self.prompt = Some(Prompt::Search);
+        }
+        self.refresh_rows();
+    }
+
+    fn filter_key(&mut self, code: KeyCode, mut text: String) {
+        match code {
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Enter => match parse_conditions(&text) {
+                Ok(conditions) => {
+                    self.filter.conditions = conditions;
+                    self.filter_text = text;
+                    self.error = None;
+                    self.refresh_rows();
+                    return;
+                }
+                Err(error) => self.error = Some(error.to_string()),
+            },
+            KeyCode::Esc => {
+                self.error = None;
+                return;
+            }
+            _ => {}
+        }
+        self.prompt = Some(Prompt::Filter(text));
+    }
+
+    /// Shortcuts available, or the text being typed
+    fn help(&self) -> String {
+        match &self.prompt {
+            Some(Prompt::Search) => format!("/{}  (Enter keep, Esc clear)", self.filter.search),
+            Some(Prompt::Filter(text)) => match &self.error {
+                Some(error) => format!("filter: {text}  ({error})"),
+                None => format!(
+                    "filter: {text}  (e.g. complexity>20 churn>=5, Enter apply, Esc cancel)"
+                ),
+            },
+            None if self.source.is_some() => {
+                "↑↓ PgUp PgDn scroll  s/Esc close source  q quit".to_string()
+            }
+            None => {
+                let mut help = String::from("↑↓ PgUp PgDn Home End move  1-4 sort  / search  ");
+                help.push_str("f filter  Enter details  ");
+                if self.sources_on_disk {
+                    help.push_str("s source  ");
+                }
+                help.push_str("m axis  l log scale  q quit");
+                if !self.filter.is_empty() {
+                    help.push_str("  Esc clear filters");
+                }
+                help
+            }
+        }
+    }
+
+    /// Open the source of the selected file, or close it when open. The working directory may
+    /// hold other versions of the files than those of an analysed revision or archive, so their
+    /// source is never shown
+    pub fn toggle_source(&mut self) {
+        self.source = match self.source {
+            Some(_) => None,
+            None if self.sources_on_disk => self.selected_metric().map(SourceView::open),
+            None => None,
+        };
+    }
+
+    /// Open or close the details of the selected file
+    pub fn toggle_details(&mut self) {
+        self.show_details = !self.show_details;
+    }
+
+    /// Switch both axes of the chart between linear and logarithmic scales
+    pub fn toggle_log_scale(&mut self) {
+        self.scale = match self.scale {
+            AxisScale::Linear => AxisScale::Logarithmic,
+            AxisScale::Logarithmic => AxisScale::Linear,
+        };
+    }
+
+    /// Switch the vertical axis of the chart, CRAP being offered only with coverage data
+    pub fn next_y_metric(&mut self) {
+        self.y_metric = match self.y_metric {
+            YAxisMetric::Complexity if self.metrics.has_coverage() => YAxisMetric::Crap,
+            _ => YAxisMetric::Complexity,
+        };
+    }
+
+    fn point(&self, metric: &FileMetrics) -> Option<(f64, f64)> {
+        self.y_metric
+            .value(metric)
+            .map(|y| (metric.churn.as_f64(), y))
     }
 
     pub fn next(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
-                if i >= self.metrics.file_metrics().len() - 1 {
+                if i >= self.rows.len() - 1 {
                     0
                 } else {
                     i + 1
@@ -51,10 +387,13 @@ impl App {
     }
 
     pub fn previous(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.metrics.file_metrics().len() - 1
+                    self.rows.len() - 1
                 } else {
                     i - 1
                 }
@@ -63,12 +402,34 @@ impl App {
         };
         self.state.select(Some(i));
     }
+
+    /// Move the selection by `offset` rows, stopping at the first and last ones
+    pub fn move_by(&mut self, offset: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let i = self.state.selected().unwrap_or_default() as isize + offset;
+        self.state
+            .select(Some(i.clamp(0, self.rows.len() as isize - 1) as usize));
+    }
+
+    pub fn first(&mut self) {
+        if !self.rows.is_empty() {
+            self.state.select(Some(0));
+        }
+    }
+
+    pub fn last(&mut self) {
+        if !self.rows.is_empty() {
+            self.state.select(Some(self.rows.len() - 1));
+        }
+    }
 }
 
 impl FileMetrics {
     fn to_cells(&self) -> Vec<Cell<'static>> {
         vec![
-            Cell::from(self.filename.clone()),
+            Cell::from(self.display_name()),
             Cell::from(self.churn.to_string()),
             Cell::from(self.complexity.to_string()),
             Cell::from(self.magnitude().to_string()),
@@ -76,7 +437,7 @@ impl FileMetrics {
     }
 }
 
-pub fn run_app(metrics: ProjectMetrics, mut writer: impl std::io::Write) {
+pub fn run_app(metrics: ProjectMetrics, sources_on_disk: bool, mut writer: impl std::io::Write) {
     enable_raw_mode().expect("raw mode");
 
     execute!(writer, EnterAlternateScreen, EnableMouseCapture)
@@ -86,7 +447,8 @@ pub fn run_app(metrics: ProjectMetrics, mut writer: impl std::io::Write) {
 
     let mut terminal = Terminal::new(backend).expect("terminal backend");
 
-    let app = App::new(metrics);
+    let mut app = App::new(metrics);
+    app.sources_on_disk = sources_on_disk;
     let _ = run_terminal_app(&mut terminal, app);
 
     disable_raw_mode().expect("Disable raw mode");
@@ -104,66 +466,163 @@ fn run_terminal_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io:
     loop {
         terminal.draw(|f| ui(f, &mut app))?;
 
-        if let Event::Key(key) = event::read()? {
-            match key.code {
-                KeyCode::Char('q') => return Ok(()),
-                KeyCode::Down => app.next(),
-                KeyCode::Up => app.previous(),
-                _ => {}
-            }
+        match event::read()? {
+            Event::Key(key) if !app.handle_key(key.code) => return Ok(()),
+            Event::Mouse(mouse) => app.handle_mouse(mouse),
+            _ => {}
         }
     }
 }
 
 fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let screen = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .margin(1)
+        .split(f.size());
     let rects = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
-        .margin(1)
-        .split(f.size());
-
-    let t = file_table(&app.metrics.file_metrics());
-    f.render_stateful_widget(t, rects[0], &mut app.state);
+        .split(screen[0]);
+    f.render_widget(
+        Paragraph::new(app.help()).style(Style::default().fg(Color::DarkGray)),
+        screen[1],
+    );
+
+    let shown: Vec<_> = app
+        .rows
+        .iter()
+        .map(|index| &app.metrics.file_metrics()[*index])
+        .collect();
+    let selected_metric = app.state.selected().and_then(|row| shown.get(row)).copied();
+
+    let left = if app.show_details {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Percentage(40),
+                    Constraint::Min(8),
+                    Constraint::Length(5),
+                ]
+                .as_ref(),
+            )
+            .split(rects[0])
+    } else {
+        vec![rects[0]]
+    };
+    let title = if app.filter.is_empty() {
+        "Files".to_string()
+    } else {
+        format!(
+            "Files ({} of {})",
+            shown.len(),
+            app.metrics.file_metrics().len()
+        )
+    };
+    let t = file_table(&shown, app.filter.sort, title);
+    f.render_stateful_widget(t, left[0], &mut app.state);
+    app.table_area = left[0];
+    app.table_offset = scroll_offset(app.table_offset, app.state.selected(), shown.len(), left[0]);
+    app.plot = None;
+    if let [_, details, sparkline] = left[..] {
+        let explanation = selected_metric
+            .and_then(|metric| app.metrics.explain(&metric.filename, DETAIL_FUNCTION_COUNT));
+        f.render_widget(detail_pane(explanation.as_ref()), details);
+        f.render_widget(churn_sparkline(selected_metric), sparkline);
+    }
 
-    let selected_metric = app
-        .state
-        .selected()
-        .and_then(|selected_index| app.metrics.file_metrics().get(selected_index));
+    if let Some(source) = &app.source {
+        f.render_widget(source.widget(), rects[1]);
+        return;
+    }
 
-    let churn_sum = app.metrics.churn_sum();
     let maximum_churn = app.metrics.maximum_churn();
-    let maximum_complexity = app.metrics.maximum_complexity();
-    let complexity_sum = app.metrics.complexity_sum();
-
-    let complexity_threshold =
-        complexity_sum as f64 / app.metrics.file_metrics().len() as f64 / 2.0;
-    let churn_threshold = churn_sum as f64 / app.metrics.file_metrics().len() as f64 / 2.0;
-
-    let threshold_points: Vec<(f64, f64)> = (1..(maximum_churn as i64 + 10))
-        .into_iter()
-        .map(|x| {
-            (
-                x as f64,
-                complexity_churn_threshold(x as f64, complexity_threshold, churn_threshold),
-            )
-        })
+    let points: Vec<_> = shown
+        .iter()
+        .filter_map(|m| app.point(m).map(|point| (age_color(m.age_days), point)))
         .collect();
+    let maximum_y = points.iter().map(|(_, (_, y))| *y).fold(0.0, f64::max);
+    let x_axis = AxisTicks::new(app.scale, maximum_churn);
+    let y_axis = AxisTicks::new(app.scale, maximum_y);
+    let plotted = |(x, y): (f64, f64)| (x_axis.position(x), y_axis.position(y));
+
+    let threshold_points: Vec<_> = match app.y_metric {
+        YAxisMetric::Complexity => threshold_points(&app.metrics, maximum_churn + 9.0),
+        YAxisMetric::Crap => vec![],
+    }
+    .into_iter()
+    .map(plotted)
+    .collect();
 
     let selected_point: Vec<(f64, f64)> = selected_metric
-        .cloned()
         .into_iter()
-        .map(|m| m.to_point())
+        .filter_map(|m| app.point(m))
+        .map(plotted)
         .collect();
-    let points: Vec<_> = app
-        .metrics
-        .file_metrics()
-        .iter()
-        .map(|m| m.to_point())
+    let metric_data: Vec<_> = [Color::Green, Color::Yellow, Color::Red]
+        .into_iter()
+        .map(|color| {
+            let group: Vec<_> = points
+                .iter()
+                .filter(|(point_color, _)| *point_color == color)
+                .map(|(_, point)| plotted(*point))
+                .collect();
+            (color, filter_out_selected_metric(&group, &selected_point))
+        })
         .collect();
-    let metric_data = filter_out_selected_metric(&points, &selected_point);
     let datasets = create_datasets(&threshold_points, &metric_data, &selected_point);
-    let graph = create_chart(datasets, maximum_churn, maximum_complexity + 10.0);
+    let graph = create_chart(datasets, &x_axis, &y_axis, app.y_metric.title());
     f.render_widget(graph, rects[1]);
+
+    // The selected file is named in full and marked on the axes, the main hotspots by their
+    // short name when there is room
+    let plot = PlotArea::new(rects[1], x_axis, y_axis);
+    let selected = selected_metric.and_then(|metric| Some((metric, app.point(metric)?)));
+    let mut hotspots: Vec<_> = shown
+        .iter()
+        .filter(|metric| selected_metric != Some(**metric))
+        .filter_map(|metric| Some((*metric, app.point(metric)?)))
+        .collect();
+    hotspots.sort_by(|(a, _), (b, _)| b.magnitude().total_cmp(&a.magnitude()));
+    hotspots.truncate(HOTSPOT_LABEL_COUNT);
+    let selected_style = Style::default().fg(Color::Black).bg(Color::Magenta);
+    let labels: Vec<_> = selected
+        .map(|(metric, point)| (point, metric.display_name(), selected_style))
+        .into_iter()
+        .chain(hotspots.into_iter().map(|(metric, point)| {
+            let name = short_name(&metric.filename).to_string();
+            (point, name, Style::default().fg(Color::White))
+        }))
+        .collect();
+    let areas = plot.place_labels(
+        &labels
+            .iter()
+            .map(|(point, name, _)| (*point, name.chars().count() as u16))
+            .collect::<Vec<_>>(),
+    );
+    for ((_, name, style), area) in labels.into_iter().zip(areas) {
+        if let Some(area) = area {
+            f.render_widget(Paragraph::new(name).style(style), area);
+        }
+    }
+    if let Some((_, point)) = selected {
+        for (area, mark) in plot.axis_marks(point) {
+            let mark = Paragraph::new(mark).style(Style::default().fg(Color::Magenta));
+            f.render_widget(mark, area);
+        }
+    }
+    app.plot = Some(plot);
+}
+
+/// Files changed in the last month are red, in the last six months yellow, and older or
+/// undated ones green
+fn age_color(age_days: Option<i64>) -> Color {
+    match age_days {
+        Some(age) if age <= 30 => Color::Red,
+        Some(age) if age <= 180 => Color::Yellow,
+        _ => Color::Green,
+    }
 }
 
 fn filter_out_selected_metric(
@@ -171,7 +630,7 @@ fn filter_out_selected_metric(
     selected_metric: &[(f64, f64)],
 ) -> Vec<(f64, f64)> {
     metric_data
-        .into_iter()
+        .iter()
         .filter(|metric| !selected_metric.contains(metric))
         .cloned()
         .collect()
@@ -179,7 +638,7 @@ fn filter_out_selected_metric(
 
 fn create_datasets<'a>(
     threshold_points: &'a [(f64, f64)],
-    metric_data: &'a [(f64, f64)],
+    metric_data: &'a [(Color, Vec<(f64, f64)>)],
     selected_point: &'a [(f64, f64)],
 ) -> Vec<Dataset<'a>> {
     let threshold_points = Dataset::default()
@@ -187,20 +646,22 @@ fn create_datasets<'a>(
         .style(Style::default().fg(Color::DarkGray))
         .graph_type(GraphType::Line)
         .data(threshold_points);
-    let metric_data = Dataset::default()
-        .marker(symbols::Marker::Dot)
-        .style(
-            Style::default()
-                .fg(Color::Green)
-                .add_modifier(Modifier::BOLD),
-        )
-        .data(metric_data);
+    let metric_data = metric_data.iter().map(|(color, points)| {
+        Dataset::default()
+            .marker(symbols::Marker::Dot)
+            .style(Style::default().fg(*color).add_modifier(Modifier::BOLD))
+            .data(points)
+    });
     let selected_point = Dataset::default()
         .marker(symbols::Marker::Block)
         .style(Style::default().fg(Color::Magenta))
         .graph_type(GraphType::Scatter)
         .data(selected_point);
-    vec![threshold_points, metric_data, selected_point]
+
+    let mut datasets = vec![threshold_points];
+    datasets.extend(metric_data);
+    datasets.push(selected_point);
+    datasets
 }
 
 #[cfg(test)]
@@ -221,31 +682,30 @@ mod tests {
     #[test]
     fn test_create_datasets() {
         let threshold_points = vec![(1.0, 2.0), (3.0, 4.0)];
-        let metric_data = vec![(15.0, 20.0), (10.0, 30.0)];
+        let metric_data = vec![
+            (Color::Green, vec![(15.0, 20.0), (10.0, 30.0)]),
+            (Color::Red, vec![(5.0, 2.0)]),
+        ];
 
         let selected_point = vec![(20.0, 10.0)];
         let result = create_datasets(&threshold_points, &metric_data, &selected_point);
-        assert_eq!(result.len(), 3);
+        assert_eq!(result.len(), 4);
+    }
+
+    #[test]
+    fn recent_files_stand_out() {
+        assert_eq!(age_color(Some(3)), Color::Red);
+        assert_eq!(age_color(Some(90)), Color::Yellow);
+        assert_eq!(age_color(Some(400)), Color::Green);
+        assert_eq!(age_color(None), Color::Green);
     }
 
     #[test]
     fn app_selection_next() {
         let metric_data = vec![
-            FileMetrics {
-                churn: Churn::from(15),
-                complexity: 20.0,
-                filename: "foo.rs".to_string(),
-            },
-            FileMetrics {
-                churn: Churn::from(10),
-                complexity: 30.0,
-                filename: "foo.rs".to_string(),
-            },
-            FileMetrics {
-                churn: Churn::from(20),
-                complexity: 10.0,
-                filename: "foo.rs".to_string(),
-            },
+            FileMetrics::new("foo.rs".to_string(), Churn::from(15), 20.0),
+            FileMetrics::new("foo.rs".to_string(), Churn::from(10), 30.0),
+            FileMetrics::new("foo.rs".to_string(), Churn::from(20), 10.0),
         ];
 
         let metrics = ProjectMetrics::new(metric_data);
@@ -268,21 +728,9 @@ mod tests {
     #[test]
     fn app_selection_previous() {
         let metric_data = vec![
-            FileMetrics {
-                churn: Churn::from(15),
-                complexity: 20.0,
-                filename: "foo.rs".to_string(),
-            },
-            FileMetrics {
-                churn: Churn::from(10),
-                complexity: 30.0,
-                filename: "foo.rs".to_string(),
-            },
-            FileMetrics {
-                churn: Churn::from(20),
-                complexity: 10.0,
-                filename: "foo.rs".to_string(),
-            },
+            FileMetrics::new("foo.rs".to_string(), Churn::from(15), 20.0),
+            FileMetrics::new("foo.rs".to_string(), Churn::from(10), 30.0),
+            FileMetrics::new("foo.rs".to_string(), Churn::from(20), 10.0),
         ];
 
         let metrics = ProjectMetrics::new(metric_data);
@@ -301,4 +749,204 @@ mod tests {
 
         assert!(app.state.selected().is_some());
     }
+
+    fn type_keys(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.handle_key(KeyCode::Char(c));
+        }
+    }
+
+    fn selected_filename(app: &App) -> Option<&str> {
+        app.selected_metric().map(|metric| metric.filename.as_str())
+    }
+
+    #[test]
+    fn search_filter_and_sort_the_table() {
+        let mut app = App::new(ProjectMetrics::new(vec![
+            FileMetrics::new("src/main.rs".to_string(), Churn::from(10), 5.0),
+            FileMetrics::new("src/metrics.rs".to_string(), Churn::from(3), 40.0),
+            FileMetrics::new("README.md".to_string(), Churn::from(7), 0.0),
+        ]));
+        app.handle_key(KeyCode::End);
+        assert_eq!(selected_filename(&app), Some("README.md"));
+
+        type_keys(&mut app, "2");
+        assert_eq!(app.rows, vec![0, 2, 1]);
+        assert_eq!(selected_filename(&app), Some("README.md"));
+
+        type_keys(&mut app, "/met");
+        assert_eq!(app.rows, vec![1]);
+        assert_eq!(selected_filename(&app), Some("src/metrics.rs"));
+        app.handle_key(KeyCode::Esc);
+        assert_eq!(app.rows.len(), 3);
+
+        type_keys(&mut app, "fcomplexity>");
+        app.handle_key(KeyCode::Enter);
+        assert!(app.help().starts_with("filter: complexity>  ("));
+        assert!(app.error.is_some());
+        type_keys(&mut app, "1");
+        app.handle_key(KeyCode::Enter);
+        assert_eq!(app.rows, vec![0, 1]);
+        assert!(app.prompt.is_none());
+
+        app.handle_key(KeyCode::Esc);
+        assert_eq!(app.rows.len(), 3);
+        assert!(!app.handle_key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn page_through_the_table() {
+        let metrics = (0..25)
+            .map(|index| FileMetrics::new(format!("{index}.rs"), Churn::from(1), 1.0))
+            .collect();
+        let mut app = App::new(ProjectMetrics::new(metrics));
+
+        app.handle_key(KeyCode::PageDown);
+        assert_eq!(app.state.selected(), Some(PAGE_ROWS));
+        app.handle_key(KeyCode::PageDown);
+        app.handle_key(KeyCode::PageDown);
+        assert_eq!(app.state.selected(), Some(24));
+        app.handle_key(KeyCode::PageUp);
+        assert_eq!(app.state.selected(), Some(14));
+        app.handle_key(KeyCode::Home);
+        assert_eq!(app.state.selected(), Some(0));
+
+        type_keys(&mut app, "/nothing");
+        assert_eq!(app.state.selected(), None);
+        app.handle_key(KeyCode::Enter);
+        app.handle_key(KeyCode::Down);
+        app.handle_key(KeyCode::End);
+        assert_eq!(app.state.selected(), None);
+    }
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: crossterm::event::KeyModifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn click_rows_and_points() {
+        let mut app = App::new(ProjectMetrics::new(vec![
+            FileMetrics::new("a.rs".to_string(), Churn::from(1), 1.0),
+            FileMetrics::new("b.rs".to_string(), Churn::from(20), 40.0),
+            FileMetrics::new("c.rs".to_string(), Churn::from(10), 20.0),
+        ]));
+        let mut terminal =
+            Terminal::new(tui::backend::TestBackend::new(100, 30)).expect("terminal");
+        terminal.draw(|f| ui(f, &mut app)).expect("drawn");
+        let click = MouseEventKind::Down(MouseButton::Left);
+
+        // Below the border, the header and the first row with its margin
+        let table = app.table_area;
+        app.handle_mouse(mouse(click, table.x + 2, table.y + 5));
+        assert_eq!(app.state.selected(), Some(1));
+        app.handle_mouse(mouse(MouseEventKind::ScrollDown, table.x + 2, table.y + 5));
+        assert_eq!(app.state.selected(), Some(2));
+
+        let plot = app.plot.clone().expect("chart drawn");
+        let (column, row) = plot.position((20.0, 40.0)).expect("point shown");
+        app.handle_mouse(mouse(click, column + 1, row));
+        assert_eq!(app.state.selected(), Some(1));
+
+        terminal.draw(|f| ui(f, &mut app)).expect("drawn");
+        let buffer = terminal.backend().buffer();
+        assert_ne!(buffer.get(column, row).symbol, " ");
+        let tooltip: String = (column + 2..column + 6)
+            .map(|x| buffer.get(x, row).symbol.clone())
+            .collect();
+        assert_eq!(tooltip, "b.rs");
+    }
+
+    fn screen_text(terminal: &Terminal<tui::backend::TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area().width as usize)
+            .map(|line| {
+                line.iter()
+                    .map(|cell| cell.symbol.as_str())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn label_hotspots_and_mark_the_selection() {
+        let mut app = App::new(ProjectMetrics::new(vec![
+            FileMetrics::new("src/a.rs".to_string(), Churn::from(2), 1.0),
+            FileMetrics::new("src/deep/b.rs".to_string(), Churn::from(30), 40.0),
+            FileMetrics::new("src/c.rs".to_string(), Churn::from(300), 12.0),
+        ]));
+        let mut terminal =
+            Terminal::new(tui::backend::TestBackend::new(120, 40)).expect("terminal");
+        app.next();
+
+        terminal.draw(|f| ui(f, &mut app)).expect("drawn");
+        let screen = screen_text(&terminal);
+        assert!(screen.contains("src/a.rs"));
+        // Named in the table, then by its short name on the chart
+        assert_eq!(screen.matches("b.rs").count(), 2);
+        assert!(screen.contains('▲') && screen.contains('▶'));
+
+        app.handle_key(KeyCode::Char('l'));
+        terminal.draw(|f| ui(f, &mut app)).expect("drawn");
+        let screen = screen_text(&terminal);
+        assert!(screen.contains("Churn (log)"));
+        assert!(screen.contains("1000"));
+    }
+
+    #[test]
+    fn enter_toggles_details() {
+        let mut app = App::new(ProjectMetrics::new(vec![]));
+        assert!(!app.show_details);
+
+        app.toggle_details();
+        assert!(app.show_details);
+        app.toggle_details();
+        assert!(!app.show_details);
+    }
+
+    #[test]
+    fn source_of_the_selected_file() {
+        let metric = FileMetrics::new("Cargo.toml".to_string(), Churn::from(1), 1.0);
+        let mut app = App::new(ProjectMetrics::new(vec![metric]));
+
+        app.toggle_source();
+        assert!(app.source.is_none());
+
+        app.next();
+        app.toggle_source();
+        assert!(app.source.is_some());
+        app.toggle_source();
+        assert!(app.source.is_none());
+
+        app.sources_on_disk = false;
+        app.toggle_source();
+        assert!(app.source.is_none());
+        assert!(!app.help().contains("s source"));
+    }
+
+    #[test]
+    fn crap_axis_needs_coverage() {
+        let mut metric = FileMetrics::new("foo.rs".to_string(), Churn::from(15), 20.0);
+        let mut app = App::new(ProjectMetrics::new(vec![metric.clone()]));
+
+        app.next_y_metric();
+        assert_eq!(app.y_metric, YAxisMetric::Complexity);
+
+        metric.coverage = Some(0.0);
+        let mut app = App::new(ProjectMetrics::new(vec![metric.clone()]));
+
+        app.next_y_metric();
+        assert_eq!(app.y_metric, YAxisMetric::Crap);
+        assert_eq!(app.point(&metric), Some((15.0, 420.0)));
+
+        app.next_y_metric();
+        assert_eq!(app.y_metric, YAxisMetric::Complexity);
+    }
 }
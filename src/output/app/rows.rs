@@ -0,0 +1,218 @@
+use std::cmp::Ordering;
+
+use eyre::{eyre, Result};
+
+use crate::metrics::FileMetrics;
+
+/// Column of the file table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Name,
+    Churn,
+    Complexity,
+    Magnitude,
+}
+
+impl Column {
+    pub const ALL: [Column; 4] = [
+        Column::Name,
+        Column::Churn,
+        Column::Complexity,
+        Column::Magnitude,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Column::Name => "Filename",
+            Column::Churn => "Churn",
+            Column::Complexity => "Complexity",
+            Column::Magnitude => "Magnitude",
+        }
+    }
+
+    /// Numeric value of the column, `None` for the name
+    fn value(self, metric: &FileMetrics) -> Option<f64> {
+        match self {
+            Column::Name => None,
+            Column::Churn => Some(metric.churn.as_f64()),
+            Column::Complexity => Some(metric.complexity),
+            Column::Magnitude => Some(metric.magnitude()),
+        }
+    }
+
+    fn compare(self, a: &FileMetrics, b: &FileMetrics) -> Ordering {
+        match (self.value(a), self.value(b)) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            _ => a.filename.cmp(&b.filename),
+        }
+    }
+}
+
+/// Comparison of a column with a number, such as `complexity>20`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Condition {
+    column: Column,
+    operator: &'static str,
+    value: f64,
+}
+
+impl Condition {
+    const OPERATORS: [&'static str; 5] = [">=", "<=", ">", "<", "="];
+
+    fn parse(text: &str) -> Result<Self> {
+        let position = text
+            .find(['<', '>', '='])
+            .ok_or_else(|| eyre!("missing comparison in {text}, such as complexity>20"))?;
+        let (name, rest) = text.split_at(position);
+        let operator = Self::OPERATORS
+            .into_iter()
+            .find(|operator| rest.starts_with(operator))
+            .ok_or_else(|| eyre!("unknown comparison in {text}"))?;
+        let column = match name.trim().to_lowercase().as_str() {
+            "churn" => Column::Churn,
+            "complexity" => Column::Complexity,
+            "magnitude" => Column::Magnitude,
+            other => {
+                return Err(eyre!(
+                    "unknown metric {other}, use churn, complexity or magnitude"
+                ))
+            }
+        };
+        let value = rest[operator.len()..]
+            .trim()
+            .parse()
+            .map_err(|_| eyre!("{} is not a number", &rest[operator.len()..]))?;
+        Ok(Self {
+            column,
+            operator,
+            value,
+        })
+    }
+
+    fn matches(&self, metric: &FileMetrics) -> bool {
+        let Some(value) = self.column.value(metric) else {
+            return false;
+        };
+        match self.operator {
+            ">=" => value >= self.value,
+            "<=" => value <= self.value,
+            ">" => value > self.value,
+            "<" => value < self.value,
+            _ => value == self.value,
+        }
+    }
+}
+
+/// Conditions separated by spaces, all of which must hold
+pub fn parse_conditions(text: &str) -> Result<Vec<Condition>> {
+    text.split_whitespace().map(Condition::parse).collect()
+}
+
+/// Whether the characters of `query` appear in order in `filename`, ignoring case
+pub fn fuzzy_match(query: &str, filename: &str) -> bool {
+    let mut characters = filename.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|wanted| characters.any(|c| c == wanted))
+}
+
+/// Order and selection of the rows of the file table
+#[derive(Debug, Clone, Default)]
+pub struct RowFilter {
+    /// Sorted column and whether it is descending, `None` keeping the order of the analysis
+    pub sort: Option<(Column, bool)>,
+    pub search: String,
+    pub conditions: Vec<Condition>,
+}
+
+impl RowFilter {
+    /// Sort by `column`, reversing the order when it is already sorted. Numbers start from the
+    /// highest and names from the first
+    pub fn sort_by(&mut self, column: Column) {
+        let descending = match self.sort {
+            Some((sorted, descending)) if sorted == column => !descending,
+            _ => column != Column::Name,
+        };
+        self.sort = Some((column, descending));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.search.is_empty() && self.conditions.is_empty()
+    }
+
+    /// Positions in `metrics` of the shown files, in the order they are shown
+    pub fn rows(&self, metrics: &[FileMetrics]) -> Vec<usize> {
+        let mut rows: Vec<_> = (0..metrics.len())
+            .filter(|index| {
+                let metric = &metrics[*index];
+                fuzzy_match(&self.search, &metric.filename)
+                    && self
+                        .conditions
+                        .iter()
+                        .all(|condition| condition.matches(metric))
+            })
+            .collect();
+        if let Some((column, descending)) = self.sort {
+            rows.sort_by(|a, b| {
+                let order = column.compare(&metrics[*a], &metrics[*b]);
+                if descending {
+                    order.reverse()
+                } else {
+                    order
+                }
+            });
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Churn;
+
+    use super::*;
+
+    fn metrics() -> Vec<FileMetrics> {
+        vec![
+            FileMetrics::new("src/main.rs".to_string(), Churn::from(10), 5.0),
+            FileMetrics::new("src/metrics.rs".to_string(), Churn::from(3), 40.0),
+            FileMetrics::new("README.md".to_string(), Churn::from(7), 0.0),
+        ]
+    }
+
+    #[test]
+    fn sort_by_columns() {
+        let metrics = metrics();
+        let mut filter = RowFilter::default();
+        assert_eq!(filter.rows(&metrics), vec![0, 1, 2]);
+
+        filter.sort_by(Column::Churn);
+        assert_eq!(filter.rows(&metrics), vec![0, 2, 1]);
+        filter.sort_by(Column::Churn);
+        assert_eq!(filter.rows(&metrics), vec![1, 2, 0]);
+        filter.sort_by(Column::Name);
+        assert_eq!(filter.rows(&metrics), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn search_and_filter() {
+        let metrics = metrics();
+        let mut filter = RowFilter {
+            search: "SMrs".to_string(),
+            ..RowFilter::default()
+        };
+        assert_eq!(filter.rows(&metrics), vec![0, 1]);
+
+        filter.conditions = parse_conditions("complexity>20 churn<=3").expect("conditions");
+        assert_eq!(filter.rows(&metrics), vec![1]);
+    }
+
+    #[test]
+    fn reject_invalid_conditions() {
+        assert!(parse_conditions("complexity").is_err());
+        assert!(parse_conditions("name>3").is_err());
+        assert!(parse_conditions("churn>many").is_err());
+        assert_eq!(parse_conditions("").expect("no condition"), vec![]);
+    }
+}
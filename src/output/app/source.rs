@@ -0,0 +1,328 @@
+use std::fs;
+
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+};
+
+use crate::metrics::{FileMetrics, FunctionMetrics};
+
+/// Lines scrolled by page up and page down
+pub const PAGE_LINES: u16 = 20;
+
+/// Lines shown above the most complex function when the source is opened
+const CONTEXT_LINES: usize = 2;
+
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+/// Source of a file, with the metrics of each function above its first line and a gutter
+/// coloured by the complexity of the function around each line
+pub struct SourceView {
+    filename: String,
+    lines: Vec<Spans<'static>>,
+    scroll: u16,
+}
+
+impl SourceView {
+    /// Read the source of `metric` from the working directory
+    pub fn open(metric: &FileMetrics) -> Self {
+        let content = match fs::read(&metric.filename) {
+            Ok(content) => String::from_utf8_lossy(&content).into_owned(),
+            Err(error) => format!("// Unable to read {}: {error}", metric.filename),
+        };
+        Self::new(metric, &content)
+    }
+
+    /// Annotate `content`, starting at the most complex function
+    pub fn new(metric: &FileMetrics, content: &str) -> Self {
+        let functions: Vec<_> = metric
+            .functions
+            .iter()
+            .filter(|function| function.name != "<anonymous>")
+            .collect();
+        let hotspot = functions
+            .iter()
+            .max_by(|a, b| a.cyclomatic.total_cmp(&b.cyclomatic))
+            .map(|function| function.start_line);
+
+        // Only Rust sources are highlighted, other languages having other keywords and literals
+        let is_rust = metric.filename.ends_with(".rs");
+        let mut lines = vec![];
+        let mut scroll = 0;
+        for (index, line) in content.lines().enumerate() {
+            let number = index + 1;
+            if hotspot == Some(number) {
+                scroll = lines.len().saturating_sub(CONTEXT_LINES);
+            }
+            for function in functions.iter().filter(|f| f.start_line == number) {
+                lines.push(annotation(function));
+            }
+            let heat = innermost_function(&metric.functions, number)
+                .map(|function| Span::styled("▌ ", Style::default().fg(heat_color(function))))
+                .unwrap_or_else(|| Span::raw("  "));
+            let mut spans = vec![
+                Span::styled(
+                    format!("{number:>5} "),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                heat,
+            ];
+            if is_rust {
+                spans.extend(highlight(line));
+            } else {
+                spans.push(Span::raw(line.to_string()));
+            }
+            lines.push(Spans::from(spans));
+        }
+
+        Self {
+            filename: metric.filename.clone(),
+            lines,
+            scroll: scroll as u16,
+        }
+    }
+
+    pub fn scroll_down(&mut self, lines: u16) {
+        let last = self.lines.len().saturating_sub(1) as u16;
+        self.scroll = self.scroll.saturating_add(lines).min(last);
+    }
+
+    pub fn scroll_up(&mut self, lines: u16) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    pub fn widget(&self) -> Paragraph<'static> {
+        Paragraph::new(self.lines.clone())
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("{} (s to close)", self.filename)),
+            )
+            .scroll((self.scroll, 0))
+    }
+}
+
+/// Metrics of a function, shown above its first line
+fn annotation(function: &FunctionMetrics) -> Spans<'static> {
+    let mut text = format!(
+        "{:6}▼ {}: cyclomatic {}, cognitive {}",
+        "", function.name, function.cyclomatic, function.cognitive
+    );
+    if let Some(commits) = function.blame_commits {
+        text.push_str(&format!(", {commits} blamed commits"));
+    }
+    Spans::from(Span::styled(
+        text,
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::ITALIC),
+    ))
+}
+
+/// Most nested function containing the line `number`, functions being sorted by start line
+fn innermost_function(functions: &[FunctionMetrics], number: usize) -> Option<&FunctionMetrics> {
+    functions
+        .iter()
+        .rev()
+        .find(|function| function.start_line <= number && number <= function.end_line)
+}
+
+fn heat_color(function: &FunctionMetrics) -> Color {
+    match function.cyclomatic {
+        c if c <= 5.0 => Color::Green,
+        c if c <= 10.0 => Color::Yellow,
+        _ => Color::Red,
+    }
+}
+
+/// Colour the Rust keywords, strings, characters and comments of a line, each line being read
+/// on its own
+fn highlight(line: &str) -> Vec<Span<'static>> {
+    let mut spans = vec![];
+    let mut plain = String::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if line[start..].starts_with("//") {
+            push_plain(&mut spans, &mut plain);
+            spans.push(Span::styled(
+                line[start..].to_string(),
+                Style::default().fg(Color::DarkGray),
+            ));
+            return spans;
+        } else if c == '"' {
+            push_plain(&mut spans, &mut plain);
+            let mut end = line.len();
+            let mut escaped = false;
+            for (index, c) in chars.by_ref() {
+                if c == '"' && !escaped {
+                    end = index + 1;
+                    break;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            spans.push(Span::styled(
+                line[start..end].to_string(),
+                Style::default().fg(Color::Green),
+            ));
+        } else if let Some(length) = char_literal_length(&line[start..]) {
+            push_plain(&mut spans, &mut plain);
+            let end = start + length;
+            while chars.next_if(|(index, _)| *index < end).is_some() {}
+            spans.push(Span::styled(
+                line[start..end].to_string(),
+                Style::default().fg(Color::Green),
+            ));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start + c.len_utf8();
+            while let Some((index, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || *c == '_') {
+                    break;
+                }
+                end = index + c.len_utf8();
+                chars.next();
+            }
+            let word = &line[start..end];
+            if KEYWORDS.contains(&word) {
+                push_plain(&mut spans, &mut plain);
+                spans.push(Span::styled(
+                    word.to_string(),
+                    Style::default().fg(Color::Magenta),
+                ));
+            } else {
+                plain.push_str(word);
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    push_plain(&mut spans, &mut plain);
+    spans
+}
+
+/// Length of the character literal, such as `'"'` or `'\''`, starting `text`, `None` when it
+/// starts with something else, such as a lifetime
+fn char_literal_length(text: &str) -> Option<usize> {
+    let mut chars = text.strip_prefix('\'')?.char_indices();
+    let (_, first) = chars.next()?;
+    let (index, _) = if first == '\\' {
+        // The escaped character, then up to the closing quote for escapes such as `\u{1F980}`
+        chars.next()?;
+        chars.find(|(_, c)| *c == '\'')?
+    } else {
+        chars.next().filter(|(_, c)| *c == '\'' && first != '\'')?
+    };
+    // Both quotes are one byte long
+    Some(index + 2)
+}
+
+fn push_plain(spans: &mut Vec<Span<'static>>, plain: &mut String) {
+    if !plain.is_empty() {
+        spans.push(Span::raw(std::mem::take(plain)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Churn;
+
+    use super::*;
+
+    fn text(spans: &Spans) -> String {
+        spans.0.iter().map(|span| span.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn annotate_functions() {
+        let mut metric = FileMetrics::new("foo.rs".to_string(), Churn::from(1), 13.0);
+        metric.functions = vec![
+            FunctionMetrics {
+                blame_commits: Some(2),
+                ..FunctionMetrics::new("a", 1, 1, 1.0)
+            },
+            FunctionMetrics {
+                blame_commits: Some(2),
+                ..FunctionMetrics::new("b", 3, 6, 12.0)
+            },
+            FunctionMetrics::new("<anonymous>", 4, 4, 1.0),
+        ];
+        let content = "fn a() {}\n\nfn b() {\n    let c = || 1;\n    // done\n}\n";
+
+        let view = SourceView::new(&metric, content);
+
+        let lines: Vec<_> = view.lines.iter().map(text).collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(
+            lines[0],
+            "      ▼ a: cyclomatic 1, cognitive 0, 2 blamed commits"
+        );
+        assert_eq!(lines[1], "    1 ▌ fn a() {}");
+        assert_eq!(lines[2], "    2   ");
+        assert_eq!(
+            lines[3],
+            "      ▼ b: cyclomatic 12, cognitive 0, 2 blamed commits"
+        );
+        assert_eq!(view.scroll, 1);
+        assert_eq!(view.lines[4].0[1].style.fg, Some(Color::Red));
+        assert_eq!(view.lines[5].0[1].style.fg, Some(Color::Green));
+    }
+
+    #[test]
+    fn scroll_within_the_source() {
+        let metric = FileMetrics::new("foo.rs".to_string(), Churn::from(1), 1.0);
+        let mut view = SourceView::new(&metric, "a\nb\nc\n");
+
+        view.scroll_down(PAGE_LINES);
+        assert_eq!(view.scroll, 2);
+        view.scroll_up(1);
+        assert_eq!(view.scroll, 1);
+        view.scroll_up(PAGE_LINES);
+        assert_eq!(view.scroll, 0);
+    }
+
+    #[test]
+    fn highlight_characters_but_not_lifetimes() {
+        let spans = highlight(r#"match c { '"' | '\'' => x::<'a>(), _ => "'" }"#);
+
+        let green: Vec<_> = spans
+            .iter()
+            .filter(|span| span.style.fg == Some(Color::Green))
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(green, vec![r#"'"'"#, r"'\''", r#""'""#]);
+    }
+
+    #[test]
+    fn highlight_only_rust_sources() {
+        let metric = FileMetrics::new("script.py".to_string(), Churn::from(1), 1.0);
+        let view = SourceView::new(&metric, "if x: # \"fn\"");
+
+        assert_eq!(view.lines[0].0.len(), 3);
+        assert_eq!(view.lines[0].0[2].style.fg, None);
+    }
+
+    #[test]
+    fn highlight_keywords_strings_and_comments() {
+        let spans = highlight(r#"let s = "a \" b"; // fn"#);
+
+        let styled: Vec<_> = spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style.fg))
+            .collect();
+        assert_eq!(
+            styled,
+            vec![
+                ("let", Some(Color::Magenta)),
+                (" s = ", None),
+                (r#""a \" b""#, Some(Color::Green)),
+                ("; ", None),
+                ("// fn", Some(Color::DarkGray)),
+            ]
+        );
+    }
+}
@@ -1,29 +1,50 @@
 use tui::{
-    layout::Constraint,
+    layout::{Constraint, Rect},
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, Cell, Row, Table},
 };
 
 use crate::metrics::FileMetrics;
 
-pub fn file_table(items: &[FileMetrics]) -> Table {
+use super::rows::Column;
+
+/// Lines of a row, without its bottom margin
+const ROW_HEIGHT: u16 = 1;
+/// Lines of a row and its bottom margin
+const ROW_LINES: u16 = 2;
+/// Lines of the header and its bottom margin
+const HEADER_LINES: u16 = 2;
+
+/// Table of `items`, the sorted column being marked by an arrow
+pub fn file_table<'a>(
+    items: &[&'a FileMetrics],
+    sort: Option<(Column, bool)>,
+    title: String,
+) -> Table<'a> {
     let selected_style = Style::default().add_modifier(Modifier::REVERSED);
     let normal_style = Style::default().add_modifier(Modifier::BOLD);
-    let header_cells = ["Filename", "Churn", "Complexity", "Magnitude"]
-        .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Red)));
+    let header_cells = Column::ALL.into_iter().map(|column| {
+        let title = match sort {
+            Some((sorted, true)) if sorted == column => format!("{} ▼", column.title()),
+            Some((sorted, false)) if sorted == column => format!("{} ▲", column.title()),
+            _ => column.title().to_string(),
+        };
+        Cell::from(title).style(Style::default().fg(Color::Red))
+    });
     let header = Row::new(header_cells)
         .style(normal_style)
-        .height(1)
-        .bottom_margin(1);
+        .height(ROW_HEIGHT)
+        .bottom_margin(HEADER_LINES - ROW_HEIGHT);
 
     let rows = items.iter().map(|item| {
         let cells = item.to_cells();
-        Row::new(cells).height(1_u16).bottom_margin(1)
+        Row::new(cells)
+            .height(ROW_HEIGHT)
+            .bottom_margin(ROW_LINES - ROW_HEIGHT)
     });
     Table::new(rows)
         .header(header)
-        .block(Block::default().borders(Borders::ALL).title("Files"))
+        .block(Block::default().borders(Borders::ALL).title(title))
         .highlight_style(selected_style)
         .highlight_symbol(">> ")
         .widths(&[
@@ -34,6 +55,71 @@ pub fn file_table(items: &[FileMetrics]) -> Table {
         ])
 }
 
+/// First row shown once the table is rendered in `area`, scrolled the way the table scrolls
+/// itself since its state keeps it private
+pub fn scroll_offset(
+    offset: usize,
+    selected: Option<usize>,
+    row_count: usize,
+    area: Rect,
+) -> usize {
+    let Some(selected) = selected else {
+        return 0;
+    };
+    if row_count == 0 {
+        return offset;
+    }
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    let max_height = inner.height.saturating_sub(HEADER_LINES);
+
+    let mut start = offset.min(row_count - 1);
+    let mut end = start;
+    let mut height: u16 = 0;
+    while end < row_count && height + ROW_HEIGHT <= max_height {
+        height += ROW_LINES;
+        end += 1;
+    }
+    let selected = selected.min(row_count - 1);
+    while selected >= end {
+        height = height.saturating_add(ROW_LINES);
+        end += 1;
+        while height > max_height {
+            height = height.saturating_sub(ROW_LINES);
+            start += 1;
+        }
+    }
+    while selected < start {
+        start -= 1;
+        height = height.saturating_add(ROW_LINES);
+        // The table drops rows from the end, which does not move the first one
+        while height > max_height {
+            height = height.saturating_sub(ROW_LINES);
+        }
+    }
+    start
+}
+
+/// Row shown at the cell `(column, line)` of a table rendered in `area`
+pub fn row_at(
+    area: Rect,
+    offset: usize,
+    row_count: usize,
+    column: u16,
+    line: u16,
+) -> Option<usize> {
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    let first_line = inner.top() + HEADER_LINES;
+    if column < inner.left()
+        || column >= inner.right()
+        || line < first_line
+        || line >= inner.bottom()
+    {
+        return None;
+    }
+    let row = offset + usize::from((line - first_line) / ROW_LINES);
+    (row < row_count).then_some(row)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Churn;
@@ -43,19 +129,39 @@ mod tests {
     // This tests nothing but Table is not a testable struct since all fields are private and no method allow access.
     #[test]
     fn create_file_table() {
-        let items = vec![
-            FileMetrics {
-                filename: "file1.txt".to_string(),
-                churn: Churn::from(15),
-                complexity: 20.0,
-            },
-            FileMetrics {
-                filename: "file2.txt".to_string(),
-                churn: Churn::from(10),
-                complexity: 30.0,
-            },
-        ];
-
-        let _table = file_table(&items);
+        let file1 = FileMetrics::new("file1.txt".to_string(), Churn::from(15), 20.0);
+        let file2 = FileMetrics::new("file2.txt".to_string(), Churn::from(10), 30.0);
+        let items = [&file1, &file2];
+
+        let _table = file_table(&items, None, "Files".to_string());
+        let _sorted = file_table(
+            &items,
+            Some((Column::Churn, true)),
+            "Files (2 of 3)".to_string(),
+        );
+    }
+
+    #[test]
+    fn follow_the_table_scrolling() {
+        // Borders and header leave room for 4 rows and their margins
+        let area = Rect::new(0, 0, 30, 12);
+
+        assert_eq!(scroll_offset(0, Some(3), 20, area), 0);
+        assert_eq!(scroll_offset(0, Some(4), 20, area), 1);
+        assert_eq!(scroll_offset(5, Some(9), 20, area), 6);
+        assert_eq!(scroll_offset(6, Some(2), 20, area), 2);
+        assert_eq!(scroll_offset(6, None, 20, area), 0);
+    }
+
+    #[test]
+    fn find_clicked_rows() {
+        let area = Rect::new(0, 0, 30, 12);
+
+        assert_eq!(row_at(area, 0, 20, 5, 3), Some(0));
+        assert_eq!(row_at(area, 0, 20, 5, 4), Some(0));
+        assert_eq!(row_at(area, 2, 20, 5, 9), Some(5));
+        assert_eq!(row_at(area, 0, 2, 5, 9), None);
+        assert_eq!(row_at(area, 0, 20, 5, 2), None);
+        assert_eq!(row_at(area, 0, 20, 0, 3), None);
     }
 }
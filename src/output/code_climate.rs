@@ -0,0 +1,77 @@
+use serde_json::{json, Value};
+
+use crate::thresholds::{Rule, Severity, Violation};
+
+/// Code Climate issues, the format of GitLab code-quality reports
+pub fn code_climate_report(violations: &[Violation]) -> Value {
+    let issues: Vec<Value> = violations
+        .iter()
+        .map(|violation| {
+            let begin = violation.start_line();
+            let end = violation.end_line().unwrap_or(begin);
+            json!({
+                "type": "issue",
+                "check_name": format!("quality-time/{}", violation.rule.id()),
+                "description": violation.message(),
+                "categories": [category(violation.rule)],
+                "severity": severity(violation.severity),
+                "fingerprint": violation.fingerprint(),
+                "location": {
+                    "path": violation.filename,
+                    "lines": { "begin": begin, "end": end },
+                },
+            })
+        })
+        .collect();
+
+    Value::Array(issues)
+}
+
+fn category(rule: Rule) -> &'static str {
+    match rule {
+        Rule::Complexity | Rule::Magnitude => "Complexity",
+        Rule::Churn => "Bug Risk",
+    }
+}
+
+fn severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Note => "minor",
+        Severity::Warning => "major",
+        Severity::Error => "critical",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        metrics::FunctionMetrics,
+        thresholds::{Rule, Severity, Violation},
+    };
+
+    use super::code_climate_report;
+
+    #[test]
+    fn violation_becomes_an_issue() {
+        let violation = Violation {
+            filename: "src/foo.rs".to_string(),
+            function: Some(FunctionMetrics::new("parse", 12, 40, 18.0)),
+            occurrence: 0,
+            rule: Rule::Complexity,
+            value: 18.0,
+            threshold: 10.0,
+            magnitude: 18.1,
+            severity: Severity::Warning,
+        };
+
+        let report = code_climate_report(std::slice::from_ref(&violation));
+
+        let issue = &report[0];
+        assert_eq!(issue["check_name"], "quality-time/high-complexity");
+        assert_eq!(issue["severity"], "major");
+        assert_eq!(issue["fingerprint"], violation.fingerprint());
+        assert_eq!(issue["location"]["path"], "src/foo.rs");
+        assert_eq!(issue["location"]["lines"]["begin"], 12);
+        assert_eq!(issue["location"]["lines"]["end"], 40);
+    }
+}
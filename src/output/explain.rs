@@ -0,0 +1,158 @@
+use crate::metrics::Explanation;
+
+use super::{format_date, format_percent};
+
+/// Number of line owners listed in an explanation
+const OWNER_COUNT: usize = 5;
+
+/// Text report of everything known about one file
+pub fn explanation_lines(explanation: &Explanation) -> Vec<String> {
+    let metric = explanation.metric;
+    let mut lines = vec![
+        metric.display_name(),
+        format!(
+            "Rank {} of {}, at or above {:.0}% of the files",
+            explanation.rank,
+            explanation.file_count,
+            explanation.percentile * 100.0
+        ),
+        format!("Quadrant: {}", explanation.quadrant),
+        format!(
+            "Churn: {}, complexity: {}, magnitude: {:.2}",
+            metric.churn,
+            metric.complexity,
+            metric.magnitude()
+        ),
+    ];
+    if let Some(coverage) = metric.coverage {
+        let risk = metric.risk().unwrap_or_default();
+        lines.push(format!(
+            "Coverage: {}, risk: {risk:.2}",
+            format_percent(Some(coverage))
+        ));
+    }
+    if let Some(bugfixes) = metric.bugfixes {
+        let density = metric
+            .defect_density()
+            .map(|density| format!("{density:.2}"))
+            .unwrap_or_default();
+        lines.push(format!("Fixes: {bugfixes}, defect density: {density}"));
+    }
+    if let (Some(first), Some(last)) = (metric.first_commit, metric.last_commit) {
+        lines.push(format!(
+            "First change: {}, last change: {} ({} days ago)",
+            format_date(first),
+            format_date(last),
+            metric.age_days.unwrap_or_default()
+        ));
+    }
+    if metric.departed_share.is_some() {
+        lines.push(format!(
+            "Changes by departed authors: {}",
+            format_percent(metric.departed_share)
+        ));
+    }
+
+    if !explanation.top_functions.is_empty() {
+        lines.push(String::new());
+        lines.push("Most complex functions".to_string());
+        for function in &explanation.top_functions {
+            lines.push(format!(
+                "  {} (lines {}-{}): cyclomatic {}, cognitive {}",
+                function.name,
+                function.start_line,
+                function.end_line,
+                function.cyclomatic,
+                function.cognitive
+            ));
+        }
+    }
+
+    let total_lines: usize = metric.owners.iter().map(|owner| owner.lines).sum();
+    if total_lines > 0 {
+        lines.push(String::new());
+        lines.push("Line owners".to_string());
+        for owner in metric.owners.iter().take(OWNER_COUNT) {
+            let share = owner.lines as f64 / total_lines as f64;
+            lines.push(format!(
+                "  {:>5.1}% {} ({} lines)",
+                share * 100.0,
+                owner.author,
+                owner.lines
+            ));
+        }
+        if metric.owners.len() > OWNER_COUNT {
+            lines.push(format!("  and {} more", metric.owners.len() - OWNER_COUNT));
+        }
+    }
+
+    if !metric.recent_commits.is_empty() {
+        lines.push(String::new());
+        lines.push("Recent commits".to_string());
+        for commit in &metric.recent_commits {
+            lines.push(format!(
+                "  {} {} {}: {}",
+                format_date(commit.time),
+                commit.id.chars().take(8).collect::<String>(),
+                commit.author,
+                commit.summary
+            ));
+        }
+    }
+
+    lines
+}
+
+/// Bars of a text sparkline, from the lowest to the highest value
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One bar per value, scaled to the highest one
+pub fn sparkline(values: &[u64]) -> String {
+    let maximum = values.iter().copied().max().unwrap_or_default().max(1);
+    values
+        .iter()
+        .map(|value| BARS[(*value * (BARS.len() as u64 - 1) / maximum) as usize])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        metrics::{CommitSummary, FileMetrics, LineOwner, ProjectMetrics},
+        Churn,
+    };
+
+    use super::{explanation_lines, sparkline};
+
+    #[test]
+    fn describe_the_file() {
+        let mut metric = FileMetrics::new("foo.rs".to_string(), Churn::from(3), 4.0);
+        metric.recent_commits = vec![CommitSummary {
+            id: "0123456789abcdef".to_string(),
+            time: 0,
+            author: "Alice".to_string(),
+            summary: "fix: foo".to_string(),
+        }];
+        metric.owners = (0..7)
+            .map(|index| LineOwner::new(&format!("author {index}"), 10 - index))
+            .collect();
+        let metrics = ProjectMetrics::new(vec![metric]);
+
+        let lines = explanation_lines(&metrics.explain("foo.rs", 3).expect("explanation"));
+
+        assert_eq!(lines[1], "Rank 1 of 1, at or above 100% of the files");
+        assert_eq!(lines[3], "Churn: 3, complexity: 4, magnitude: 5.00");
+        assert!(lines.contains(&"   20.4% author 0 (10 lines)".to_string()));
+        assert!(lines.contains(&"  and 2 more".to_string()));
+        assert_eq!(
+            lines.last().expect("commit line"),
+            "  1970-01-01 01234567 Alice: fix: foo"
+        );
+    }
+
+    #[test]
+    fn scaled_bars() {
+        assert_eq!(sparkline(&[0, 1, 2, 4]), "▁▂▄█");
+        assert_eq!(sparkline(&[0, 0]), "▁▁");
+    }
+}
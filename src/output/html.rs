@@ -0,0 +1,175 @@
+use std::fmt::Write;
+
+use crate::metrics::ProjectMetrics;
+
+use super::{
+    svg::{escape_xml, quadrant_color, ScatterPlot},
+    OutputOptions,
+};
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h1, h2 { font-weight: 600; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 4px 10px; text-align: left; }
+td.number { text-align: right; }
+table.sortable th { cursor: pointer; background: #f3f3f3; }
+table.sortable th:hover { background: #e3e3e3; }
+svg.scatter circle:hover { stroke: black; stroke-width: 2; fill-opacity: 1; }
+.swatch { display: inline-block; width: 10px; height: 10px; border-radius: 5px; margin-right: 6px; }
+"#;
+
+const SORT_SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable th").forEach(function (header, column) {
+  header.addEventListener("click", function () {
+    var body = header.closest("table").tBodies[0];
+    var ascending = header.dataset.order !== "asc";
+    header.dataset.order = ascending ? "asc" : "desc";
+    var rows = Array.from(body.rows);
+    rows.sort(function (a, b) {
+      var x = a.cells[column].dataset.value || a.cells[column].textContent;
+      var y = b.cells[column].dataset.value || b.cells[column].textContent;
+      var order = isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+      return ascending ? order : -order;
+    });
+    rows.forEach(function (row) { body.appendChild(row); });
+  });
+});
+"#;
+
+/// Self-contained HTML report: configuration, summary, scatter plot and sortable file table
+pub fn html_report(metrics: &ProjectMetrics, options: &OutputOptions) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Quality Time report</title>\n");
+    let _ = writeln!(html, "<style>{STYLE}</style>\n</head>\n<body>");
+    html.push_str("<h1>Quality Time report</h1>\n");
+
+    render_configuration(&mut html, options);
+    render_summary(&mut html, metrics);
+
+    html.push_str("<h2>Churn vs complexity</h2>\n");
+    html.push_str(&ScatterPlot::new(metrics).labels(options.top).render());
+
+    render_file_table(&mut html, metrics);
+
+    let _ = writeln!(html, "<script>{SORT_SCRIPT}</script>\n</body>\n</html>");
+    html
+}
+
+fn render_configuration(html: &mut String, options: &OutputOptions) {
+    if options.configuration.is_empty() {
+        return;
+    }
+    html.push_str("<h2>Configuration</h2>\n<table>\n");
+    for (key, value) in &options.configuration {
+        let _ = writeln!(
+            html,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            escape_xml(key),
+            escape_xml(value)
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+fn render_summary(html: &mut String, metrics: &ProjectMetrics) {
+    html.push_str("<h2>Summary</h2>\n<table>\n");
+    let rows = [
+        ("Files", metrics.file_metrics().len().to_string()),
+        ("Total churn", metrics.churn_sum().to_string()),
+        ("Mean churn", format!("{:.2}", metrics.churn_mean())),
+        ("Total complexity", metrics.complexity_sum().to_string()),
+        (
+            "Mean complexity",
+            format!("{:.2}", metrics.complexity_mean()),
+        ),
+    ];
+    for (label, value) in rows {
+        let _ = writeln!(
+            html,
+            "<tr><th>{label}</th><td class=\"number\">{value}</td></tr>"
+        );
+    }
+    for (quadrant, count) in metrics.quadrant_counts() {
+        let _ = writeln!(
+            html,
+            "<tr><th><span class=\"swatch\" style=\"background:{}\"></span>{quadrant}</th><td class=\"number\">{count}</td></tr>",
+            quadrant_color(quadrant)
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+fn render_file_table(html: &mut String, metrics: &ProjectMetrics) {
+    let with_package = metrics.has_packages();
+    let with_coverage = metrics.has_coverage();
+    html.push_str("<h2>Files</h2>\n<table class=\"sortable\">\n<thead><tr><th>Filename</th>");
+    if with_package {
+        html.push_str("<th>Crate</th>");
+    }
+    if with_coverage {
+        html.push_str("<th>Coverage</th><th>Risk</th>");
+    }
+    html.push_str(
+        "<th>Churn</th><th>Complexity</th><th>Magnitude</th><th>Quadrant</th></tr></thead>\n<tbody>\n",
+    );
+
+    for metric in metrics.file_metrics() {
+        let _ = write!(html, "<tr><td>{}</td>", escape_xml(&metric.filename));
+        if with_package {
+            let package = metric.package.as_deref().unwrap_or_default();
+            let _ = write!(html, "<td>{}</td>", escape_xml(package));
+        }
+        if with_coverage {
+            let coverage = metric.coverage.map(|c| c * 100.0);
+            let _ = write!(
+                html,
+                "<td class=\"number\" data-value=\"{}\">{}</td><td class=\"number\">{}</td>",
+                coverage.unwrap_or(-1.0),
+                coverage.map(|c| format!("{c:.1}%")).unwrap_or_default(),
+                metric.risk().map(|r| format!("{r:.2}")).unwrap_or_default(),
+            );
+        }
+        let _ = writeln!(
+            html,
+            "<td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\" data-value=\"{magnitude}\">{magnitude:.2}</td><td>{}</td></tr>",
+            metric.churn,
+            metric.complexity,
+            metrics.quadrant_of(metric),
+            magnitude = metric.magnitude(),
+        );
+    }
+    html.push_str("</tbody>\n</table>\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        metrics::{FileMetrics, ProjectMetrics},
+        output::OutputOptions,
+        Churn,
+    };
+
+    use super::html_report;
+
+    #[test]
+    fn report_is_self_contained() {
+        let metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("foo.rs".to_string(), Churn::from(4), 8.0),
+            FileMetrics::new("bar.rs".to_string(), Churn::from(1), 2.0),
+        ]);
+        let options = OutputOptions {
+            configuration: vec![("Project".to_string(), "/tmp/project".to_string())],
+            ..OutputOptions::default()
+        };
+
+        let html = html_report(&metrics, &options);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<svg"));
+        assert!(html.contains("/tmp/project"));
+        assert!(html.contains("<td>foo.rs</td>"));
+        assert!(!html.contains("src=\"http"));
+    }
+}
@@ -0,0 +1,303 @@
+use std::fmt::Write;
+
+use crate::metrics::{FileMetrics, MetricChange, ProjectMetrics};
+use crate::pr::PullRequestImpact;
+
+use super::OutputOptions;
+
+/// GitHub-flavoured Markdown report meant to be posted on merge requests or wiki pages
+pub fn markdown_report(metrics: &ProjectMetrics, options: &OutputOptions) -> String {
+    let mut markdown = String::from("## Quality Time report\n\n");
+
+    render_summary(&mut markdown, metrics);
+    render_quadrants(&mut markdown, metrics);
+    render_hotspots(&mut markdown, metrics, options.top);
+
+    if let Some(baseline) = &options.baseline {
+        render_changes(&mut markdown, &metrics.changes_since(baseline));
+    }
+
+    markdown
+}
+
+/// Review comment listing the files changed by a branch and the hotspots it makes worse
+pub fn pr_markdown_report(impact: &PullRequestImpact) -> String {
+    let mut markdown = String::from("## Quality Time impact\n\n");
+    let _ = writeln!(
+        markdown,
+        "Files changed on {} since {} (merge base `{}`).\n",
+        code(&impact.head),
+        code(&impact.base),
+        short_id(&impact.merge_base),
+    );
+
+    if impact.files.is_empty() {
+        markdown.push_str("No analysed file changed on this branch.\n");
+        return markdown;
+    }
+
+    match impact.worsened_hotspots() {
+        0 => markdown.push_str("✅ No known hotspot gets more complex.\n\n"),
+        count => {
+            let _ = writeln!(markdown, "⚠️ {count} known hotspot(s) get more complex.\n");
+        }
+    }
+
+    markdown.push_str("| File | Hotspot rank | Complexity | |\n|---|---:|---|---|\n");
+    for file in &impact.files {
+        let _ = writeln!(
+            markdown,
+            "| {} | {} | {} | {} |",
+            code(&file.filename),
+            file.rank.map(|rank| rank.to_string()).unwrap_or_default(),
+            evolution(file.complexity_before, file.complexity_after),
+            if file.worsens_hotspot() {
+                "⚠️ worse hotspot"
+            } else {
+                ""
+            },
+        );
+    }
+    markdown
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..7).unwrap_or(id)
+}
+
+fn render_summary(markdown: &mut String, metrics: &ProjectMetrics) {
+    markdown
+        .push_str("| Files | Total churn | Mean churn | Total complexity | Mean complexity |\n");
+    markdown.push_str("|---:|---:|---:|---:|---:|\n");
+    let _ = writeln!(
+        markdown,
+        "| {} | {} | {:.2} | {} | {:.2} |\n",
+        metrics.file_metrics().len(),
+        metrics.churn_sum(),
+        metrics.churn_mean(),
+        metrics.complexity_sum(),
+        metrics.complexity_mean(),
+    );
+}
+
+fn render_quadrants(markdown: &mut String, metrics: &ProjectMetrics) {
+    markdown.push_str("### Quadrants\n\n| Quadrant | Files |\n|---|---:|\n");
+    for (quadrant, count) in metrics.quadrant_counts() {
+        let _ = writeln!(markdown, "| {quadrant} | {count} |");
+    }
+    markdown.push('\n');
+}
+
+fn render_hotspots(markdown: &mut String, metrics: &ProjectMetrics, top: usize) {
+    let with_coverage = metrics.has_coverage();
+    let with_branches = with_coverage && metrics.has_branch_coverage();
+    let _ = writeln!(markdown, "### Top {top} hotspots\n");
+    if with_branches {
+        markdown.push_str("| # | File | Churn | Complexity | Magnitude | Coverage | Branch coverage | Risk |\n|---:|---|---:|---:|---:|---:|---:|---:|\n");
+    } else if with_coverage {
+        markdown.push_str("| # | File | Churn | Complexity | Magnitude | Coverage | Risk |\n|---:|---|---:|---:|---:|---:|---:|\n");
+    } else {
+        markdown
+            .push_str("| # | File | Churn | Complexity | Magnitude |\n|---:|---|---:|---:|---:|\n");
+    }
+
+    // Files come in the order of `--sort-by`
+    for (rank, metric) in metrics.file_metrics().iter().take(top).enumerate() {
+        let _ = write!(
+            markdown,
+            "| {} | {} | {} | {} | {:.2} |",
+            rank + 1,
+            code(&metric.filename),
+            metric.churn,
+            metric.complexity,
+            metric.magnitude(),
+        );
+        let percent = |rate: Option<f64>| {
+            rate.map(|c| format!("{:.1}%", c * 100.0))
+                .unwrap_or_default()
+        };
+        if with_coverage {
+            let _ = write!(markdown, " {} |", percent(metric.coverage));
+        }
+        if with_branches {
+            let _ = write!(markdown, " {} |", percent(metric.branch_coverage));
+        }
+        if with_coverage {
+            let _ = write!(
+                markdown,
+                " {} |",
+                metric.risk().map(round).unwrap_or_default()
+            );
+        }
+        markdown.push('\n');
+    }
+    markdown.push('\n');
+}
+
+fn render_changes(markdown: &mut String, changes: &[MetricChange]) {
+    markdown.push_str("### Changed in this PR\n\n");
+    if changes.is_empty() {
+        markdown.push_str("No file metric changed since the baseline.\n\n");
+        return;
+    }
+
+    markdown.push_str("| File | Churn | Complexity | Magnitude |\n|---|---|---|---|\n");
+    for change in changes {
+        let value =
+            |metric: &Option<FileMetrics>, read: fn(&FileMetrics) -> f64| metric.as_ref().map(read);
+        let _ = writeln!(
+            markdown,
+            "| {} | {} | {} | {} |",
+            code(&change.filename),
+            evolution(
+                value(&change.before, |m| m.churn.as_f64()),
+                value(&change.after, |m| m.churn.as_f64())
+            ),
+            evolution(
+                value(&change.before, |m| m.complexity),
+                value(&change.after, |m| m.complexity)
+            ),
+            evolution(
+                value(&change.before, FileMetrics::magnitude),
+                value(&change.after, FileMetrics::magnitude)
+            ),
+        );
+    }
+    markdown.push('\n');
+}
+
+/// `before → after` with an arrow telling whether the value went up or down
+fn evolution(before: Option<f64>, after: Option<f64>) -> String {
+    match (before, after) {
+        (Some(before), Some(after)) => {
+            let arrow = if after > before {
+                " ⬆️"
+            } else if after < before {
+                " ⬇️"
+            } else {
+                ""
+            };
+            format!("{} → {}{arrow}", round(before), round(after))
+        }
+        (None, Some(after)) => format!("🆕 {}", round(after)),
+        (Some(before), None) => format!("~~{}~~ removed", round(before)),
+        (None, None) => String::new(),
+    }
+}
+
+fn round(value: f64) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    rounded.to_string()
+}
+
+/// Inline code span, with table separators escaped
+fn code(text: &str) -> String {
+    format!("`{}`", text.replace('|', "\\|"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        metrics::{FileMetrics, ProjectMetrics, SortKey},
+        output::OutputOptions,
+        pr::{FileImpact, PullRequestImpact},
+        Churn,
+    };
+
+    use super::{evolution, markdown_report, pr_markdown_report};
+
+    #[test]
+    fn report_lists_top_hotspots() {
+        let mut metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("cold.rs".to_string(), Churn::from(1), 1.0),
+            FileMetrics::new("hot.rs".to_string(), Churn::from(9), 30.0),
+        ]);
+        metrics.sort_by(SortKey::Magnitude);
+        let options = OutputOptions {
+            top: 1,
+            ..OutputOptions::default()
+        };
+
+        let markdown = markdown_report(&metrics, &options);
+
+        assert!(markdown.contains("### Top 1 hotspots"));
+        assert!(markdown.contains("| 1 | `hot.rs` | 9 | 30 |"));
+        assert!(!markdown.contains("`cold.rs`"));
+        assert!(!markdown.contains("Changed in this PR"));
+    }
+
+    #[test]
+    fn report_keeps_the_order_of_the_files() {
+        let mut covered = FileMetrics::new("covered.rs".to_string(), Churn::from(9), 30.0);
+        covered.coverage = Some(1.0);
+        covered.branch_coverage = Some(0.75);
+        let mut untested = FileMetrics::new("untested.rs".to_string(), Churn::from(5), 20.0);
+        untested.coverage = Some(0.0);
+        let mut metrics = ProjectMetrics::new(vec![covered, untested]);
+        metrics.sort_by(SortKey::Risk);
+
+        let markdown = markdown_report(&metrics, &OutputOptions::default());
+
+        let untested = markdown
+            .find("| 1 | `untested.rs`")
+            .expect("riskiest file first");
+        let covered = markdown
+            .find("| 2 | `covered.rs`")
+            .expect("covered file second");
+        assert!(untested < covered);
+        assert!(markdown.contains("| Coverage | Branch coverage | Risk |"));
+        assert!(markdown.contains("| 100.0% | 75.0% |"));
+    }
+
+    #[test]
+    fn report_compares_with_baseline() {
+        let baseline = ProjectMetrics::new(vec![FileMetrics::new(
+            "foo.rs".to_string(),
+            Churn::from(1),
+            10.0,
+        )]);
+        let metrics = ProjectMetrics::new(vec![FileMetrics::new(
+            "foo.rs".to_string(),
+            Churn::from(2),
+            4.0,
+        )]);
+        let options = OutputOptions {
+            baseline: Some(baseline),
+            ..OutputOptions::default()
+        };
+
+        let markdown = markdown_report(&metrics, &options);
+
+        assert!(markdown.contains("### Changed in this PR"));
+        assert!(markdown.contains("| `foo.rs` | 1 → 2 ⬆️ | 10 → 4 ⬇️ |"));
+    }
+
+    #[test]
+    fn pr_report_flags_worse_hotspots() {
+        let impact = PullRequestImpact {
+            base: "main".to_string(),
+            head: "HEAD".to_string(),
+            merge_base: "0123456789abcdef".to_string(),
+            files: vec![FileImpact {
+                filename: "hot.rs".to_string(),
+                rank: Some(1),
+                complexity_before: Some(10.0),
+                complexity_after: Some(12.0),
+                is_hotspot: true,
+            }],
+        };
+
+        let markdown = pr_markdown_report(&impact);
+
+        assert!(markdown.contains("(merge base `0123456`)"));
+        assert!(markdown.contains("⚠️ 1 known hotspot(s)"));
+        assert!(markdown.contains("| `hot.rs` | 1 | 10 → 12 ⬆️ | ⚠️ worse hotspot |"));
+    }
+
+    #[test]
+    fn evolution_of_new_and_removed_files() {
+        assert_eq!(evolution(None, Some(3.0)), "🆕 3");
+        assert_eq!(evolution(Some(3.0), None), "~~3~~ removed");
+        assert_eq!(evolution(Some(3.0), Some(3.0)), "3 → 3");
+    }
+}
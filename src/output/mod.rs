@@ -1,13 +1,30 @@
-use crate::metrics::ProjectMetrics;
+use crate::metrics::{FileMetrics, ProjectMetrics};
+use crate::pr::PullRequestImpact;
+use crate::thresholds::{Granularity, Thresholds};
 use clap::ValueEnum;
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::Table;
-use eyre::{Context, Result};
+use eyre::{eyre, Context, Result};
 
 use self::app::run_app;
+use self::code_climate::code_climate_report;
+use self::explain::{explanation_lines, sparkline};
+use self::html::html_report;
+use self::markdown::{markdown_report, pr_markdown_report};
+use self::sarif::sarif_report;
+use self::svg::ScatterPlot;
 
 mod app;
+mod code_climate;
+mod explain;
+mod html;
+mod markdown;
+#[cfg(feature = "png")]
+mod png;
+mod sarif;
+mod scale;
+mod svg;
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum OutputMode {
@@ -15,44 +32,497 @@ pub enum OutputMode {
     StdOut,
     /// DIsplay the results with a graph in a terminal application
     Tui,
+    /// Write a self-contained HTML report with a sortable table and a scatter plot
+    Html,
+    /// Export the churn vs complexity scatter plot as an SVG image
+    Svg,
+    /// Export the churn vs complexity scatter plot as a PNG image
+    #[cfg(feature = "png")]
+    Png,
+    /// Write a GitHub-flavoured Markdown report, for merge request comments and wikis
+    Markdown,
+    /// Write the results as JSON, usable later as a `--baseline`
+    Json,
+    /// Write a SARIF 2.1.0 log of the files or functions above the thresholds
+    Sarif,
+    /// Write a Code Climate report, as read by GitLab code quality, of the files or functions above the thresholds
+    CodeClimate,
 }
 
+/// Settings tweaking how the results are rendered
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    /// Print one hotspot table per package of the workspace instead of a single table
+    pub per_package: bool,
+    /// Settings of the run, as label and value, shown in the reports that include them
+    pub configuration: Vec<(String, String)>,
+    /// Number of top files highlighted by the reports
+    pub top: usize,
+    /// Results of a previous run to compare against
+    pub baseline: Option<ProjectMetrics>,
+    /// Limits used by the code-scanning outputs
+    pub thresholds: Thresholds,
+    /// Whether the code-scanning outputs report files or functions
+    pub granularity: Granularity,
+    /// Whether the analysed files are the ones on disk, whose source the terminal application
+    /// can show, unlike those of a revision or an archive
+    pub sources_on_disk: bool,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            per_package: false,
+            configuration: vec![],
+            top: 10,
+            baseline: None,
+            thresholds: Thresholds::default(),
+            granularity: Granularity::default(),
+            sources_on_disk: true,
+        }
+    }
+}
+
+/// Number of files listed for each package in the package summary
+const PACKAGE_HOTSPOT_COUNT: usize = 5;
+
 pub fn print_output(
     output_mode: OutputMode,
     metrics: ProjectMetrics,
+    options: OutputOptions,
     mut writer: impl std::io::Write,
 ) -> Result<()> {
     match output_mode {
         OutputMode::StdOut => {
-            let mut table = Table::new();
-            table
-                .set_header(vec!["Filename", "Churn", "Complexity"])
-                .load_preset(UTF8_FULL)
-                .apply_modifier(UTF8_ROUND_CORNERS);
+            if options.per_package && metrics.has_packages() {
+                // Each table lists the files of one package
+                let columns = Columns {
+                    package: false,
+                    ..Columns::of(&metrics)
+                };
+                for summary in metrics.package_summaries(usize::MAX) {
+                    writeln!(writer, "{}", summary.name).wrap_err("unable to write on writer")?;
+                    let table = file_table(&summary.hotspots, &columns);
+                    writeln!(writer, "{table}").wrap_err("unable to write on writer")?;
+                }
+            } else {
+                writeln!(
+                    writer,
+                    "{}",
+                    file_table(metrics.file_metrics(), &Columns::of(&metrics))
+                )
+                .wrap_err("unable to write on writer")?;
+            }
+
+            if options.per_package && metrics.has_packages() {
+                writeln!(writer, "{}", package_table(&metrics))
+                    .wrap_err("unable to write on writer")?;
+            }
+            if metrics.has_owners() {
+                writeln!(writer, "{}", directory_owners_table(&metrics))
+                    .wrap_err("unable to write on writer")?;
+            }
+        }
+        OutputMode::Tui => {
+            run_app(metrics, options.sources_on_disk, writer);
+        }
+        OutputMode::Html => {
+            write!(writer, "{}", html_report(&metrics, &options))
+                .wrap_err("unable to write on writer")?;
+        }
+        OutputMode::Svg => {
+            let svg = ScatterPlot::new(&metrics).labels(options.top).render();
+            write!(writer, "{svg}").wrap_err("unable to write on writer")?;
+        }
+        #[cfg(feature = "png")]
+        OutputMode::Png => {
+            let svg = ScatterPlot::new(&metrics).labels(options.top).render();
+            writer
+                .write_all(&png::svg_to_png(&svg)?)
+                .wrap_err("unable to write on writer")?;
+        }
+        OutputMode::Markdown => {
+            write!(writer, "{}", markdown_report(&metrics, &options))
+                .wrap_err("unable to write on writer")?;
+        }
+        OutputMode::Json => {
+            serde_json::to_writer_pretty(&mut writer, &metrics)
+                .wrap_err("unable to write on writer")?;
+            writeln!(writer).wrap_err("unable to write on writer")?;
+        }
+        OutputMode::Sarif => {
+            let violations = options.thresholds.violations(&metrics, options.granularity);
+            serde_json::to_writer_pretty(&mut writer, &sarif_report(&violations))
+                .wrap_err("unable to write on writer")?;
+            writeln!(writer).wrap_err("unable to write on writer")?;
+        }
+        OutputMode::CodeClimate => {
+            let violations = options.thresholds.violations(&metrics, options.granularity);
+            serde_json::to_writer_pretty(&mut writer, &code_climate_report(&violations))
+                .wrap_err("unable to write on writer")?;
+            writeln!(writer).wrap_err("unable to write on writer")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Table of the `top` functions with the highest CRAP score
+pub fn print_crap_report(
+    metrics: &ProjectMetrics,
+    top: usize,
+    mut writer: impl std::io::Write,
+) -> Result<()> {
+    let mut table = new_table();
+    table.set_header(vec![
+        "Filename",
+        "Function",
+        "Lines",
+        "Complexity",
+        "Coverage",
+        "CRAP",
+    ]);
+
+    for (file, function) in metrics.functions_by_crap().into_iter().take(top) {
+        table.add_row(vec![
+            file.filename.clone(),
+            function.name.clone(),
+            format!("{}-{}", function.start_line, function.end_line),
+            function.cyclomatic.to_string(),
+            format_percent(function.coverage),
+            function
+                .crap()
+                .map(|crap| format!("{crap:.2}"))
+                .unwrap_or_default(),
+        ]);
+    }
+
+    writeln!(writer, "{table}").wrap_err("unable to write on writer")
+}
+
+/// Number of functions listed when explaining a file
+const EXPLAINED_FUNCTION_COUNT: usize = 5;
+
+/// Everything known about `filename`, with its churn per month as a sparkline
+pub fn print_explanation(
+    metrics: &ProjectMetrics,
+    filename: &str,
+    mut writer: impl std::io::Write,
+) -> Result<()> {
+    let explanation = metrics
+        .explain(filename, EXPLAINED_FUNCTION_COUNT)
+        .ok_or_else(|| eyre!("{filename} is not among the analysed files"))?;
+
+    for line in explanation_lines(&explanation) {
+        writeln!(writer, "{line}").wrap_err("unable to write on writer")?;
+    }
+    let monthly_churn = &explanation.metric.monthly_churn;
+    if !monthly_churn.is_empty() {
+        writeln!(
+            writer,
+            "\nChurn over the last {} months: {}",
+            monthly_churn.len(),
+            sparkline(monthly_churn)
+        )
+        .wrap_err("unable to write on writer")?;
+    }
+    Ok(())
+}
+
+/// Table of the `top` files mostly changed by departed authors, weighted by their complexity
+pub fn print_knowledge_loss_report(
+    metrics: &ProjectMetrics,
+    top: usize,
+    mut writer: impl std::io::Write,
+) -> Result<()> {
+    let mut table = new_table();
+    table.set_header(vec![
+        "Filename",
+        "Complexity",
+        "Departed changes",
+        "Knowledge loss",
+    ]);
+
+    for file in metrics.files_by_knowledge_loss().into_iter().take(top) {
+        table.add_row(vec![
+            file.filename.clone(),
+            file.complexity.to_string(),
+            format_percent(file.departed_share),
+            file.knowledge_loss()
+                .map(|loss| format!("{loss:.2}"))
+                .unwrap_or_default(),
+        ]);
+    }
 
-            for metric in metrics.file_metrics().iter() {
+    writeln!(writer, "{table}").wrap_err("unable to write on writer")
+}
+
+/// Files changed by a branch, as a terminal table or a Markdown review comment
+pub fn print_pr_report(
+    output_mode: OutputMode,
+    impact: &PullRequestImpact,
+    mut writer: impl std::io::Write,
+) -> Result<()> {
+    match output_mode {
+        OutputMode::StdOut => {
+            let mut table = new_table();
+            table.set_header(vec![
+                "Filename",
+                "Rank",
+                "Complexity before",
+                "Complexity after",
+                "Worse hotspot",
+            ]);
+            let format = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+            for file in &impact.files {
                 table.add_row(vec![
-                    &metric.filename,
-                    &metric.churn.to_string(),
-                    &metric.complexity.to_string(),
+                    file.filename.clone(),
+                    file.rank.map(|rank| rank.to_string()).unwrap_or_default(),
+                    format(file.complexity_before),
+                    format(file.complexity_after),
+                    if file.worsens_hotspot() { "yes" } else { "" }.to_string(),
                 ]);
             }
+            writeln!(writer, "{table}").wrap_err("unable to write on writer")
+        }
+        OutputMode::Markdown => {
+            write!(writer, "{}", pr_markdown_report(impact)).wrap_err("unable to write on writer")
+        }
+        _ => Err(eyre!(
+            "the pr report is only available as std-out or markdown output"
+        )),
+    }
+}
 
-            writeln!(writer, "{table}").wrap_err("unable to write on writer")?;
+/// Read results previously written with [`OutputMode::Json`]
+pub fn read_json_report(reader: impl std::io::Read) -> Result<ProjectMetrics> {
+    serde_json::from_reader(reader).wrap_err("unable to read the JSON report")
+}
+
+fn new_table() -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS);
+    table
+}
+
+/// Optional columns of the file table, shown when the project holds their metrics
+struct Columns {
+    package: bool,
+    coverage: bool,
+    branch_coverage: bool,
+    bugfixes: bool,
+    dates: bool,
+    owners: bool,
+    /// References with a churn column each
+    ref_names: Vec<String>,
+}
+
+impl Columns {
+    fn of(metrics: &ProjectMetrics) -> Self {
+        Self {
+            package: metrics.has_packages(),
+            coverage: metrics.has_coverage(),
+            branch_coverage: metrics.has_branch_coverage(),
+            bugfixes: metrics.has_bugfixes(),
+            dates: metrics.has_dates(),
+            owners: metrics.has_owners(),
+            ref_names: metrics.ref_names(),
         }
-        OutputMode::Tui => {
-            run_app(metrics, writer);
+    }
+}
+
+/// Table of the `metrics` of some files, with the `columns` of the project
+fn file_table(metrics: &[FileMetrics], columns: &Columns) -> Table {
+    let mut table = new_table();
+    let mut header = vec!["Filename".to_string(), "Churn".to_string()];
+    header.extend(
+        columns
+            .ref_names
+            .iter()
+            .map(|name| format!("Churn on {name}")),
+    );
+    header.push("Complexity".to_string());
+    if columns.package {
+        header.push("Crate".to_string());
+    }
+    if columns.coverage {
+        header.push("Coverage".to_string());
+    }
+    if columns.coverage && columns.branch_coverage {
+        header.push("Branch coverage".to_string());
+    }
+    if columns.coverage {
+        header.push("Risk".to_string());
+    }
+    if columns.bugfixes {
+        header.extend(["Fixes".to_string(), "Defect density".to_string()]);
+    }
+    if columns.dates {
+        header.extend([
+            "Last change".to_string(),
+            "Days before newest commit".to_string(),
+        ]);
+    }
+    if columns.owners {
+        header.push("Main owner".to_string());
+    }
+    table.set_header(header);
+
+    for metric in metrics.iter() {
+        let mut row = vec![metric.display_name(), metric.churn.to_string()];
+        row.extend(columns.ref_names.iter().map(|name| {
+            metric
+                .churn_per_ref
+                .get(name)
+                .map(|churn| churn.to_string())
+                .unwrap_or_default()
+        }));
+        row.push(metric.complexity.to_string());
+        if columns.package {
+            row.push(metric.package.clone().unwrap_or_default());
+        }
+        if columns.coverage {
+            row.push(format_percent(metric.coverage));
         }
+        if columns.coverage && columns.branch_coverage {
+            row.push(format_percent(metric.branch_coverage));
+        }
+        if columns.coverage {
+            row.push(
+                metric
+                    .risk()
+                    .map(|risk| format!("{risk:.2}"))
+                    .unwrap_or_default(),
+            );
+        }
+        if columns.bugfixes {
+            row.push(
+                metric
+                    .bugfixes
+                    .map(|count| count.to_string())
+                    .unwrap_or_default(),
+            );
+            row.push(
+                metric
+                    .defect_density()
+                    .map(|density| format!("{density:.2}"))
+                    .unwrap_or_default(),
+            );
+        }
+        if columns.dates {
+            row.push(metric.last_commit.map(format_date).unwrap_or_default());
+            row.push(
+                metric
+                    .age_days
+                    .map(|age| age.to_string())
+                    .unwrap_or_default(),
+            );
+        }
+        if columns.owners {
+            row.push(
+                metric
+                    .main_owner()
+                    .map(|(owner, share)| format_owner(&owner.author, Some(share)))
+                    .unwrap_or_default(),
+            );
+        }
+        table.add_row(row);
     }
 
-    Ok(())
+    table
+}
+
+/// `YYYY-MM-DD` date, in UTC, of a time in seconds since the Unix epoch
+fn format_date(seconds: i64) -> String {
+    // Civil date of a day count, after Howard Hinnant's `civil_from_days`
+    let days = seconds.div_euclid(24 * 60 * 60) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Author name followed by their share of the lines, e.g. `Alice (72.0%)`
+fn format_owner(author: &str, share: Option<f64>) -> String {
+    format!("{author} ({})", format_percent(share))
+}
+
+/// Share between 0 and 1 written as a percentage, empty when unknown
+fn format_percent(value: Option<f64>) -> String {
+    value
+        .map(|value| format!("{:.1}%", value * 100.0))
+        .unwrap_or_default()
+}
+
+/// Main line owners of each directory
+fn directory_owners_table(metrics: &ProjectMetrics) -> Table {
+    let mut table = new_table();
+    table.set_header(vec!["Directory", "Lines", "Main owner", "Other owners"]);
+
+    for directory in metrics.directory_owners() {
+        let total = directory.total_lines() as f64;
+        let share = |lines: usize| Some(lines as f64 / total);
+        let mut owners = directory
+            .owners
+            .iter()
+            .map(|owner| format_owner(&owner.author, share(owner.lines)));
+        table.add_row(vec![
+            directory.directory.clone(),
+            directory.total_lines().to_string(),
+            owners.next().unwrap_or_default(),
+            owners.collect::<Vec<_>>().join(", "),
+        ]);
+    }
+
+    table
+}
+
+fn package_table(metrics: &ProjectMetrics) -> Table {
+    let mut table = new_table();
+    table.set_header(vec!["Crate", "Files", "Churn", "Complexity", "Top hotspot"]);
+
+    for summary in metrics.package_summaries(PACKAGE_HOTSPOT_COUNT) {
+        let top_hotspot = summary
+            .hotspots
+            .first()
+            .map(|metric| metric.filename.clone())
+            .unwrap_or_default();
+        table.add_row(vec![
+            summary.name,
+            summary.file_count.to_string(),
+            summary.churn_sum.to_string(),
+            summary.complexity_sum.to_string(),
+            top_hotspot,
+        ]);
+    }
+
+    table
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::metrics::{Churn, FileMetrics, ProjectMetrics};
+    use crate::metrics::{Churn, FileMetrics, FunctionMetrics, LineOwner, ProjectMetrics};
 
-    use super::print_output;
+    use super::{format_date, print_crap_report, print_output, read_json_report, OutputOptions};
+
+    #[test]
+    fn dates_in_utc() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_704_067_199), "2023-12-31");
+    }
 
     #[test]
     fn display_to_stdout() {
@@ -61,6 +531,7 @@ mod tests {
         print_output(
             super::OutputMode::StdOut,
             ProjectMetrics::new(metrics),
+            OutputOptions::default(),
             &mut writer,
         )
         .expect("print in writer");
@@ -71,4 +542,141 @@ mod tests {
 
         assert!(content.contains("foo.rs"));
     }
+
+    #[test]
+    fn display_packages_to_stdout() {
+        let mut metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("core/lib.rs".to_string(), Churn::from(1), 1.0),
+            FileMetrics::new("cli/main.rs".to_string(), Churn::from(2), 1.0),
+        ]);
+        metrics.assign_packages(|filename| filename.split_once('/').map(|(p, _)| p.to_string()));
+        let mut writer = vec![];
+        print_output(
+            super::OutputMode::StdOut,
+            metrics.clone(),
+            OutputOptions::default(),
+            &mut writer,
+        )
+        .expect("print in writer");
+        let content = String::from_utf8(writer).expect("bytes to utf8");
+        assert!(!content.contains("Top hotspot"));
+
+        let mut writer = vec![];
+        print_output(
+            super::OutputMode::StdOut,
+            metrics,
+            OutputOptions {
+                per_package: true,
+                ..OutputOptions::default()
+            },
+            &mut writer,
+        )
+        .expect("print in writer");
+
+        let content = String::from_utf8(writer).expect("bytes to utf8");
+
+        assert!(content.contains("Crate"));
+        assert!(content.contains("Top hotspot"));
+        assert!(content.contains("cli/main.rs"));
+    }
+
+    #[test]
+    fn json_report_round_trip() {
+        let metrics = vec![FileMetrics::new("foo.rs".to_string(), Churn::from(3), 2.0)];
+        let mut writer = vec![];
+        print_output(
+            super::OutputMode::Json,
+            ProjectMetrics::new(metrics.clone()),
+            OutputOptions::default(),
+            &mut writer,
+        )
+        .expect("print in writer");
+
+        let report = read_json_report(writer.as_slice()).expect("report parsed");
+
+        assert_eq!(report.file_metrics(), &metrics);
+    }
+
+    #[test]
+    fn display_coverage_to_stdout() {
+        let mut metric = FileMetrics::new("foo.rs".to_string(), Churn::from(1), 1.0);
+        metric.coverage = Some(0.5);
+        metric.branch_coverage = Some(0.25);
+        let mut writer = vec![];
+        print_output(
+            super::OutputMode::StdOut,
+            ProjectMetrics::new(vec![metric]),
+            OutputOptions::default(),
+            &mut writer,
+        )
+        .expect("print in writer");
+
+        let content = String::from_utf8(writer).expect("bytes to utf8");
+
+        assert!(content.contains("Risk"));
+        assert!(content.contains("50.0%"));
+        assert!(content.contains("Branch coverage"));
+        assert!(content.contains("25.0%"));
+    }
+
+    #[test]
+    fn display_owners_to_stdout() {
+        let mut metric = FileMetrics::new("foo.rs".to_string(), Churn::from(1), 1.0);
+        metric.owners = vec![LineOwner::new("alice", 3), LineOwner::new("bob", 1)];
+        let mut writer = vec![];
+        print_output(
+            super::OutputMode::StdOut,
+            ProjectMetrics::new(vec![metric]),
+            OutputOptions::default(),
+            &mut writer,
+        )
+        .expect("print in writer");
+
+        let content = String::from_utf8(writer).expect("bytes to utf8");
+        let row = content
+            .lines()
+            .find(|line| line.contains("foo.rs"))
+            .expect("file row");
+
+        assert!(row.contains("alice (75.0%)"));
+    }
+
+    #[test]
+    fn crap_report_lists_functions() {
+        let mut metric = FileMetrics::new("foo.rs".to_string(), Churn::from(1), 3.0);
+        metric.functions = vec![FunctionMetrics {
+            coverage: Some(0.0),
+            ..FunctionMetrics::new("untested", 1, 9, 3.0)
+        }];
+        let mut writer = vec![];
+
+        print_crap_report(&ProjectMetrics::new(vec![metric]), 10, &mut writer)
+            .expect("print in writer");
+
+        let content = String::from_utf8(writer).expect("bytes to utf8");
+        assert!(content.contains("untested"));
+        assert!(content.contains("12.00"));
+    }
+
+    #[test]
+    fn export_svg_chart() {
+        let metrics = vec![FileMetrics::new(
+            "src/foo.rs".to_string(),
+            Churn::from(1),
+            1.0,
+        )];
+        let mut writer = vec![];
+        print_output(
+            super::OutputMode::Svg,
+            ProjectMetrics::new(metrics),
+            OutputOptions::default(),
+            &mut writer,
+        )
+        .expect("print in writer");
+
+        let content = String::from_utf8(writer).expect("bytes to utf8");
+
+        assert!(content.starts_with("<svg"));
+        assert!(content.contains(">foo.rs</text>"));
+    }
 }
@@ -0,0 +1,48 @@
+use eyre::{eyre, Context, Result};
+use resvg::{tiny_skia, usvg};
+
+/// Rasterise an SVG document into PNG bytes, using the fonts installed on the system for labels
+pub fn svg_to_png(svg: &str) -> Result<Vec<u8>> {
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+
+    let tree = usvg::Tree::from_str(svg, &options).wrap_err("unable to parse the chart SVG")?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or_else(|| {
+        eyre!(
+            "unable to allocate a {}x{} image",
+            size.width(),
+            size.height()
+        )
+    })?;
+
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+    pixmap
+        .encode_png()
+        .wrap_err("unable to encode the chart as PNG")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        metrics::{FileMetrics, ProjectMetrics},
+        output::svg::ScatterPlot,
+        Churn,
+    };
+
+    use super::svg_to_png;
+
+    #[test]
+    fn rasterise_scatter_plot() {
+        let metrics = ProjectMetrics::new(vec![FileMetrics::new(
+            "foo.rs".to_string(),
+            Churn::from(2),
+            3.0,
+        )]);
+
+        let png = svg_to_png(&ScatterPlot::new(&metrics).render()).expect("png rendered");
+
+        assert_eq!(&png[1..4], b"PNG");
+    }
+}
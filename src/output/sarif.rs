@@ -0,0 +1,110 @@
+use serde_json::{json, Value};
+
+use crate::thresholds::{Rule, Severity, Violation};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// SARIF 2.1.0 log with one result per threshold violation
+pub fn sarif_report(violations: &[Violation]) -> Value {
+    let rules: Vec<Value> = Rule::ALL
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id(),
+                "name": rule.to_string(),
+                "shortDescription": { "text": rule.description() },
+            })
+        })
+        .collect();
+
+    let results: Vec<Value> = violations
+        .iter()
+        .map(|violation| {
+            let mut region = json!({ "startLine": violation.start_line() });
+            if let Some(end_line) = violation.end_line() {
+                region["endLine"] = json!(end_line);
+            }
+
+            json!({
+                "ruleId": violation.rule.id(),
+                "level": level(violation.severity),
+                "message": { "text": violation.message() },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": {
+                            "uri": violation.filename,
+                            "uriBaseId": "%SRCROOT%",
+                        },
+                        "region": region,
+                    }
+                }],
+                "properties": {
+                    "value": violation.value,
+                    "threshold": violation.threshold,
+                    "magnitude": violation.magnitude,
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Note => "note",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::thresholds::{Rule, Severity, Violation};
+
+    use super::sarif_report;
+
+    #[test]
+    fn violation_becomes_a_result() {
+        let violation = Violation {
+            filename: "src/foo.rs".to_string(),
+            function: None,
+            occurrence: 0,
+            rule: Rule::Complexity,
+            value: 30.0,
+            threshold: 10.0,
+            magnitude: 30.2,
+            severity: Severity::Error,
+        };
+
+        let sarif = sarif_report(&[violation]);
+
+        assert_eq!(sarif["version"], "2.1.0");
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "high-complexity");
+        assert_eq!(result["level"], "error");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/foo.rs");
+        assert_eq!(location["region"]["startLine"], 1);
+        assert_eq!(
+            sarif["runs"][0]["tool"]["driver"]["rules"]
+                .as_array()
+                .map(Vec::len),
+            Some(3)
+        );
+    }
+}
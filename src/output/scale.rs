@@ -0,0 +1,118 @@
+use crate::metrics::ProjectMetrics;
+
+pub fn complexity_churn_threshold(
+    churn: f64,
+    complexity_threshold: f64,
+    churn_threshold: f64,
+) -> f64 {
+    (100f64 / churn - churn_threshold) + complexity_threshold
+}
+
+/// Points of the threshold curve for every integer churn from 1 to `maximum_churn`
+pub fn threshold_points(metrics: &ProjectMetrics, maximum_churn: f64) -> Vec<(f64, f64)> {
+    let complexity_threshold = metrics.complexity_mean() / 2.0;
+    let churn_threshold = metrics.churn_mean() / 2.0;
+
+    (1..=(maximum_churn as i64))
+        .map(|x| {
+            (
+                x as f64,
+                complexity_churn_threshold(x as f64, complexity_threshold, churn_threshold),
+            )
+        })
+        .collect()
+}
+
+/// Round tick values covering `[0, maximum]`, spaced by 1, 2 or 5 times a power of ten.
+pub fn nice_ticks(maximum: f64, target_count: usize) -> Vec<f64> {
+    if maximum <= 0.0 || !maximum.is_finite() || target_count == 0 {
+        return vec![0.0];
+    }
+
+    let step = nice_step(maximum / target_count as f64);
+    let count = (maximum / step).ceil() as usize;
+
+    (0..=count).map(|i| i as f64 * step).collect()
+}
+
+/// Powers of ten from 1 up to the first one reaching `maximum`, at least 1 and 10.
+pub fn log_ticks(maximum: f64) -> Vec<f64> {
+    let mut ticks = vec![1.0, 10.0];
+    while ticks.last().is_some_and(|tick| *tick < maximum) {
+        ticks.push(ticks[ticks.len() - 1] * 10.0);
+    }
+    ticks
+}
+
+/// How values are spread along an axis
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AxisScale {
+    #[default]
+    Linear,
+    /// Each power of ten takes the same room, for skewed values
+    Logarithmic,
+}
+
+impl AxisScale {
+    /// Evenly spread ticks covering `maximum`
+    pub fn ticks(self, maximum: f64, target_count: usize) -> Vec<f64> {
+        match self {
+            AxisScale::Linear => nice_ticks(maximum, target_count),
+            AxisScale::Logarithmic => log_ticks(maximum),
+        }
+    }
+
+    /// Where `value` is drawn, values below one sitting on the first logarithmic tick
+    pub fn position(self, value: f64) -> f64 {
+        match self {
+            AxisScale::Linear => value,
+            AxisScale::Logarithmic => value.max(1.0).log10(),
+        }
+    }
+}
+
+fn nice_step(raw_step: f64) -> f64 {
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+    let normalized = raw_step / magnitude;
+    let nice = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{complexity_churn_threshold, log_ticks, nice_ticks, AxisScale};
+
+    #[test]
+    fn threshold() {
+        let result = complexity_churn_threshold(2.0, 10.0, 10.0);
+        assert!(result > 0.0);
+        assert!(result < 100.0);
+    }
+
+    #[test]
+    fn ticks_are_round_and_cover_the_maximum() {
+        assert_eq!(
+            nice_ticks(100.0, 5),
+            vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0]
+        );
+        assert_eq!(nice_ticks(7.0, 4), vec![0.0, 2.0, 4.0, 6.0, 8.0]);
+        assert_eq!(nice_ticks(0.0, 4), vec![0.0]);
+    }
+
+    #[test]
+    fn logarithmic_ticks() {
+        assert_eq!(log_ticks(0.0), vec![1.0, 10.0]);
+        assert_eq!(log_ticks(250.0), vec![1.0, 10.0, 100.0, 1000.0]);
+        assert_eq!(AxisScale::Logarithmic.position(100.0), 2.0);
+        assert_eq!(AxisScale::Logarithmic.position(0.0), 0.0);
+        assert_eq!(AxisScale::Linear.position(7.0), 7.0);
+    }
+}
@@ -0,0 +1,292 @@
+use std::fmt::Write;
+
+use crate::metrics::{ProjectMetrics, Quadrant};
+
+use super::scale::{nice_ticks, threshold_points};
+
+const MARGIN_LEFT: f64 = 70.0;
+const MARGIN_RIGHT: f64 = 30.0;
+const MARGIN_TOP: f64 = 30.0;
+const MARGIN_BOTTOM: f64 = 60.0;
+
+/// Churn vs complexity scatter plot rendered as a standalone SVG document
+pub struct ScatterPlot<'a> {
+    metrics: &'a ProjectMetrics,
+    width: f64,
+    height: f64,
+    label_count: usize,
+}
+
+impl<'a> ScatterPlot<'a> {
+    pub fn new(metrics: &'a ProjectMetrics) -> Self {
+        Self {
+            metrics,
+            width: 800.0,
+            height: 500.0,
+            label_count: 0,
+        }
+    }
+
+    /// Write the name of the `count` files with the highest magnitude next to their point
+    pub fn labels(mut self, count: usize) -> Self {
+        self.label_count = count;
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let churn_max = self
+            .metrics
+            .file_metrics()
+            .iter()
+            .map(|metric| metric.churn.as_f64())
+            .fold(0.0, f64::max);
+        let x_ticks = nice_ticks(churn_max, 8);
+        let y_ticks = nice_ticks(self.metrics.maximum_complexity(), 6);
+        let x_max = x_ticks.last().copied().unwrap_or(1.0).max(1.0);
+        let y_max = y_ticks.last().copied().unwrap_or(1.0).max(1.0);
+        let frame = Frame {
+            left: MARGIN_LEFT,
+            right: self.width - MARGIN_RIGHT,
+            top: MARGIN_TOP,
+            bottom: self.height - MARGIN_BOTTOM,
+            x_max,
+            y_max,
+        };
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" class="scatter" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+            w = self.width,
+            h = self.height,
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="{}" height="{}" fill="white"/>"#,
+            self.width, self.height
+        );
+
+        let _ = writeln!(
+            svg,
+            r#"<defs><clipPath id="plot-area"><rect x="{l}" y="{t}" width="{w}" height="{h}"/></clipPath></defs>"#,
+            l = frame.left,
+            t = frame.top,
+            w = frame.right - frame.left,
+            h = frame.bottom - frame.top,
+        );
+
+        self.render_axes(&mut svg, &frame, &x_ticks, &y_ticks);
+        self.render_quadrant_lines(&mut svg, &frame);
+        self.render_threshold(&mut svg, &frame);
+        self.render_points(&mut svg, &frame);
+        self.render_labels(&mut svg, &frame);
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn render_axes(&self, svg: &mut String, frame: &Frame, x_ticks: &[f64], y_ticks: &[f64]) {
+        let _ = writeln!(
+            svg,
+            r##"<g class="axes" stroke="#444">
+<line x1="{l}" y1="{b}" x2="{r}" y2="{b}"/>
+<line x1="{l}" y1="{b}" x2="{l}" y2="{t}"/>
+</g>"##,
+            l = frame.left,
+            r = frame.right,
+            t = frame.top,
+            b = frame.bottom,
+        );
+
+        for tick in x_ticks {
+            let x = frame.x(*tick);
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{x:.1}" y1="{b}" x2="{x:.1}" y2="{b5}" stroke="#444"/><text x="{x:.1}" y="{b18}" text-anchor="middle">{tick}</text>"##,
+                b = frame.bottom,
+                b5 = frame.bottom + 5.0,
+                b18 = frame.bottom + 18.0,
+            );
+        }
+        for tick in y_ticks {
+            let y = frame.y(*tick);
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{l5}" y1="{y:.1}" x2="{l}" y2="{y:.1}" stroke="#444"/><text x="{l8}" y="{y4:.1}" text-anchor="end">{tick}</text>"##,
+                l = frame.left,
+                l5 = frame.left - 5.0,
+                l8 = frame.left - 8.0,
+                y4 = y + 4.0,
+            );
+        }
+
+        let _ = writeln!(
+            svg,
+            r#"<text x="{x:.1}" y="{y:.1}" text-anchor="middle" font-weight="bold">Churn</text>"#,
+            x = (frame.left + frame.right) / 2.0,
+            y = self.height - 15.0,
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text transform="translate(18 {y:.1}) rotate(-90)" text-anchor="middle" font-weight="bold">Complexity</text>"#,
+            y = (frame.top + frame.bottom) / 2.0,
+        );
+    }
+
+    fn render_quadrant_lines(&self, svg: &mut String, frame: &Frame) {
+        let x = frame.x(self.metrics.churn_mean());
+        let y = frame.y(self.metrics.complexity_mean());
+        let _ = writeln!(
+            svg,
+            r##"<g class="quadrants" stroke="#999" stroke-dasharray="6 4">
+<line x1="{x:.1}" y1="{t}" x2="{x:.1}" y2="{b}"/>
+<line x1="{l}" y1="{y:.1}" x2="{r}" y2="{y:.1}"/>
+</g>"##,
+            l = frame.left,
+            r = frame.right,
+            t = frame.top,
+            b = frame.bottom,
+        );
+    }
+
+    fn render_threshold(&self, svg: &mut String, frame: &Frame) {
+        let points: Vec<String> = threshold_points(self.metrics, frame.x_max)
+            .into_iter()
+            .map(|(churn, complexity)| {
+                format!("{:.1},{:.1}", frame.x(churn), frame.y_unclamped(complexity))
+            })
+            .collect();
+        if points.is_empty() {
+            return;
+        }
+        let _ = writeln!(
+            svg,
+            r##"<polyline class="threshold" points="{}" fill="none" stroke="#777" stroke-width="1.5" clip-path="url(#plot-area)"/>"##,
+            points.join(" ")
+        );
+    }
+
+    fn render_labels(&self, svg: &mut String, frame: &Frame) {
+        let mut hotspots: Vec<_> = self.metrics.file_metrics().iter().collect();
+        hotspots.sort_by(|a, b| b.magnitude().total_cmp(&a.magnitude()));
+
+        svg.push_str("<g class=\"labels\" font-size=\"11\">\n");
+        for metric in hotspots.into_iter().take(self.label_count) {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{x:.1}" y="{y:.1}">{name}</text>"#,
+                x = frame.x(metric.churn.as_f64()) + 7.0,
+                y = frame.y(metric.complexity) - 7.0,
+                name = escape_xml(short_name(&metric.filename)),
+            );
+        }
+        svg.push_str("</g>\n");
+    }
+
+    fn render_points(&self, svg: &mut String, frame: &Frame) {
+        svg.push_str("<g class=\"points\">\n");
+        for metric in self.metrics.file_metrics() {
+            let quadrant = self.metrics.quadrant_of(metric);
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{x:.1}" cy="{y:.1}" r="5" fill="{color}" fill-opacity="0.75"><title>{name}&#10;churn: {churn}, complexity: {complexity}</title></circle>"#,
+                x = frame.x(metric.churn.as_f64()),
+                y = frame.y(metric.complexity),
+                color = quadrant_color(quadrant),
+                name = escape_xml(&metric.filename),
+                churn = metric.churn,
+                complexity = metric.complexity,
+            );
+        }
+        svg.push_str("</g>\n");
+    }
+}
+
+struct Frame {
+    left: f64,
+    right: f64,
+    top: f64,
+    bottom: f64,
+    x_max: f64,
+    y_max: f64,
+}
+
+impl Frame {
+    fn x(&self, value: f64) -> f64 {
+        self.left + (value / self.x_max).clamp(0.0, 1.0) * (self.right - self.left)
+    }
+
+    fn y(&self, value: f64) -> f64 {
+        self.bottom - (value / self.y_max).clamp(0.0, 1.0) * (self.bottom - self.top)
+    }
+
+    /// Vertical position left unclamped, for lines cut by the plot area clip path
+    fn y_unclamped(&self, value: f64) -> f64 {
+        self.bottom - (value / self.y_max) * (self.bottom - self.top)
+    }
+}
+
+/// Last component of a path, short enough to label a point
+pub fn short_name(filename: &str) -> &str {
+    filename.rsplit('/').next().unwrap_or(filename)
+}
+
+pub fn quadrant_color(quadrant: Quadrant) -> &'static str {
+    match quadrant {
+        Quadrant::Hotspot => "#d62728",
+        Quadrant::ComplexStable => "#ff7f0e",
+        Quadrant::ChurningSimple => "#1f77b4",
+        Quadrant::Healthy => "#2ca02c",
+    }
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::{Churn, FileMetrics, ProjectMetrics};
+
+    use super::{escape_xml, ScatterPlot};
+
+    #[test]
+    fn scatter_plot_has_a_point_per_file() {
+        let metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("a<b>.rs".to_string(), Churn::from(3), 4.0),
+            FileMetrics::new("c.rs".to_string(), Churn::from(1), 10.0),
+        ]);
+
+        let svg = ScatterPlot::new(&metrics).render();
+
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert!(svg.contains("a&lt;b&gt;.rs"));
+        assert!(svg.contains("class=\"threshold\""));
+    }
+
+    #[test]
+    fn label_top_files() {
+        let metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("src/hot.rs".to_string(), Churn::from(30), 40.0),
+            FileMetrics::new("src/cold.rs".to_string(), Churn::from(1), 1.0),
+        ]);
+
+        let svg = ScatterPlot::new(&metrics).labels(1).render();
+
+        assert!(svg.contains(">hot.rs</text>"));
+        assert!(!svg.contains(">cold.rs</text>"));
+    }
+
+    #[test]
+    fn escape_special_characters() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">&</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,56 @@
+/// Match `text` against a shell-like `pattern` where `*` matches any run of characters
+/// (including none) and `?` matches exactly one character.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wildcard_match;
+
+    #[test]
+    fn literal_patterns() {
+        assert!(wildcard_match("crates/core", "crates/core"));
+        assert!(!wildcard_match("crates/core", "crates/cli"));
+    }
+
+    #[test]
+    fn star_and_question_mark() {
+        assert!(wildcard_match("crates/*", "crates/core"));
+        assert!(wildcard_match(
+            "refs/heads/release/*",
+            "refs/heads/release/1.0"
+        ));
+        assert!(wildcard_match("*-bot", "dependabot-bot"));
+        assert!(wildcard_match("v?.0", "v1.0"));
+        assert!(!wildcard_match("v?.0", "v10.0"));
+        assert!(!wildcard_match("crates/*", "tools/core"));
+    }
+}
@@ -0,0 +1,192 @@
+use std::path::Path;
+
+use eyre::{Context, Result};
+
+use crate::git::RepositoryExplorer;
+use crate::metrics::{MetricReader, ProjectMetrics, Quadrant};
+
+/// How a branch changes one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileImpact {
+    pub filename: String,
+    /// Position of the file in the results at the head of the branch, starting at 1
+    pub rank: Option<usize>,
+    /// Complexity at the merge base, `None` when the file is added by the branch
+    pub complexity_before: Option<f64>,
+    /// Complexity at the head of the branch, `None` when the file is removed by the branch
+    pub complexity_after: Option<f64>,
+    pub is_hotspot: bool,
+}
+
+impl FileImpact {
+    /// A hotspot whose complexity goes up on the branch
+    pub fn worsens_hotspot(&self) -> bool {
+        match (self.complexity_before, self.complexity_after) {
+            (Some(before), Some(after)) => self.is_hotspot && after > before,
+            _ => false,
+        }
+    }
+}
+
+/// Files changed by a branch since it left its base
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullRequestImpact {
+    pub base: String,
+    pub head: String,
+    pub merge_base: String,
+    pub files: Vec<FileImpact>,
+}
+
+impl PullRequestImpact {
+    pub fn worsened_hotspots(&self) -> usize {
+        self.files.iter().filter(|f| f.worsens_hotspot()).count()
+    }
+}
+
+/// Compare the files changed between the merge base of `base` and `head` and `head`,
+/// ranked against the `metrics` of `head`.
+pub fn pull_request_impact(
+    explorer: &impl RepositoryExplorer,
+    reader: &impl MetricReader,
+    metrics: &ProjectMetrics,
+    base: &str,
+    head: &str,
+) -> Result<PullRequestImpact> {
+    let merge_base = explorer.merge_base(base, head).wrap_err(format!(
+        "Unable to find the merge base of {base} and {head}"
+    ))?;
+    let changed = explorer
+        .changed_files(&merge_base, head)
+        .wrap_err("Unable to list the files changed on the branch")?;
+
+    let complexity_at = |revision: &str, filename: &str| -> Result<Option<f64>> {
+        let content = explorer.file_content(revision, filename)?;
+        Ok(content
+            .and_then(|content| reader.get_cyclomatic_from_content(Path::new(filename), content)))
+    };
+
+    let mut files = vec![];
+    for filename in changed {
+        let complexity_before = complexity_at(&merge_base, &filename)?;
+        let complexity_after = complexity_at(head, &filename)?;
+        if complexity_before.is_none() && complexity_after.is_none() {
+            continue;
+        }
+
+        let current = metrics
+            .file_metrics()
+            .iter()
+            .position(|metric| metric.filename == filename);
+        let is_hotspot = current.is_some_and(|index| {
+            metrics.quadrant_of(&metrics.file_metrics()[index]) == Quadrant::Hotspot
+        });
+
+        files.push(FileImpact {
+            filename,
+            rank: current.map(|index| index + 1),
+            complexity_before,
+            complexity_after,
+            is_hotspot,
+        });
+    }
+    files.sort_by_key(|file| file.rank.unwrap_or(usize::MAX));
+
+    Ok(PullRequestImpact {
+        base: base.to_string(),
+        head: head.to_string(),
+        merge_base,
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::Path};
+
+    use eyre::Result;
+
+    use crate::{
+        metrics::{FileMetrics, MetricReader, ProjectMetrics},
+        Churn, RepositoryExplorer,
+    };
+
+    use super::pull_request_impact;
+
+    struct BranchExplorer {}
+
+    impl RepositoryExplorer for BranchExplorer {
+        fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+            Ok(HashMap::new())
+        }
+
+        fn merge_base(&self, _base: &str, _head: &str) -> Result<String> {
+            Ok("base".to_string())
+        }
+
+        fn changed_files(&self, _from: &str, _to: &str) -> Result<Vec<String>> {
+            Ok(vec![
+                "cold.rs".to_string(),
+                "hot.rs".to_string(),
+                "new.rs".to_string(),
+            ])
+        }
+
+        fn file_content(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>> {
+            let content = match (revision, path) {
+                ("base", "hot.rs") => Some("10"),
+                ("HEAD", "hot.rs") => Some("12"),
+                ("base", "cold.rs") => Some("3"),
+                ("HEAD", "cold.rs") => Some("1"),
+                ("HEAD", "new.rs") => Some("4"),
+                _ => None,
+            };
+            Ok(content.map(|c| c.as_bytes().to_vec()))
+        }
+    }
+
+    /// Reads the complexity written in the content
+    struct ContentReader {}
+
+    impl MetricReader for ContentReader {
+        fn get_cyclomatic_from_path_and_content(&self, _path: &Path) -> Option<f64> {
+            None
+        }
+
+        fn get_cyclomatic_from_content(&self, _path: &Path, content: Vec<u8>) -> Option<f64> {
+            String::from_utf8(content).ok()?.parse().ok()
+        }
+    }
+
+    #[test]
+    fn branch_worsens_a_hotspot() {
+        let metrics = ProjectMetrics::new(vec![
+            FileMetrics::new("hot.rs".to_string(), Churn::from(10), 12.0),
+            FileMetrics::new("cold.rs".to_string(), Churn::from(1), 1.0),
+        ]);
+
+        let impact = pull_request_impact(
+            &BranchExplorer {},
+            &ContentReader {},
+            &metrics,
+            "main",
+            "HEAD",
+        )
+        .expect("impact");
+
+        assert_eq!(impact.merge_base, "base");
+        let names: Vec<_> = impact.files.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(names, vec!["hot.rs", "cold.rs", "new.rs"]);
+
+        let hot = &impact.files[0];
+        assert_eq!(hot.rank, Some(1));
+        assert_eq!(
+            (hot.complexity_before, hot.complexity_after),
+            (Some(10.0), Some(12.0))
+        );
+        assert!(hot.worsens_hotspot());
+
+        assert!(!impact.files[1].worsens_hotspot());
+        assert_eq!(impact.files[2].complexity_before, None);
+        assert_eq!(impact.worsened_hotspots(), 1);
+    }
+}
@@ -0,0 +1,342 @@
+use std::{collections::HashMap, fmt::Display};
+
+use clap::ValueEnum;
+
+use crate::metrics::{Churn, FileMetrics, FunctionMetrics, ProjectMetrics};
+
+/// Limits above which a file or function is reported by the code-scanning outputs
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Thresholds {
+    pub complexity: Option<f64>,
+    pub churn: Option<Churn>,
+    pub magnitude: Option<f64>,
+}
+
+/// Whether violations are reported per file or per function
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Granularity {
+    /// Check the metrics of each file
+    #[default]
+    File,
+    /// Check the complexity of each function, combined with the churn of its file
+    Function,
+}
+
+/// Metric checked against a threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rule {
+    Complexity,
+    Churn,
+    Magnitude,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 3] = [Rule::Complexity, Rule::Churn, Rule::Magnitude];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::Complexity => "high-complexity",
+            Rule::Churn => "high-churn",
+            Rule::Magnitude => "hotspot",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::Complexity => "Cyclomatic complexity is above the configured threshold",
+            Rule::Churn => "The file changed more often than the configured threshold",
+            Rule::Magnitude => {
+                "Combined churn and complexity is above the configured threshold, refactoring it should be a priority"
+            }
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Rule::Complexity => "complexity",
+            Rule::Churn => "churn",
+            Rule::Magnitude => "magnitude",
+        };
+        f.write_str(label)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// `Error` from twice the `reference` magnitude, `Warning` above it and `Note` below.
+    pub fn from_magnitude(magnitude: f64, reference: f64) -> Self {
+        if magnitude >= 2.0 * reference {
+            Severity::Error
+        } else if magnitude >= reference {
+            Severity::Warning
+        } else {
+            Severity::Note
+        }
+    }
+}
+
+/// A metric of a file, or of one of its functions, above its threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub filename: String,
+    pub function: Option<FunctionMetrics>,
+    /// Number of functions with the same name before this one in the file
+    pub occurrence: usize,
+    pub rule: Rule,
+    pub value: f64,
+    pub threshold: f64,
+    pub magnitude: f64,
+    pub severity: Severity,
+}
+
+impl Violation {
+    pub fn start_line(&self) -> usize {
+        self.function.as_ref().map_or(1, |f| f.start_line.max(1))
+    }
+
+    pub fn end_line(&self) -> Option<usize> {
+        self.function.as_ref().map(|f| f.end_line)
+    }
+
+    /// Identifier that stays the same across runs as long as the same metric of the same file,
+    /// or function, is flagged, whatever its value and position. Functions sharing a name in a
+    /// file are told apart by their order.
+    pub fn fingerprint(&self) -> String {
+        let function = self.function.as_ref().map_or("", |f| f.name.as_str());
+        let mut key = format!("{}\0{}\0{}", self.filename, function, self.rule.id());
+        if self.occurrence > 0 {
+            key.push_str(&format!("\0{}", self.occurrence));
+        }
+        format!("{:016x}", fnv1a(key.as_bytes()))
+    }
+
+    pub fn message(&self) -> String {
+        let subject = match &self.function {
+            Some(function) => format!("Function `{}` in {}", function.name, self.filename),
+            None => self.filename.clone(),
+        };
+        format!(
+            "{subject} has a {} of {} (threshold {})",
+            self.rule,
+            round(self.value),
+            round(self.threshold)
+        )
+    }
+}
+
+impl Thresholds {
+    pub fn is_empty(&self) -> bool {
+        self.complexity.is_none() && self.churn.is_none() && self.magnitude.is_none()
+    }
+
+    /// Every threshold exceeded in `metrics`, in the order of the files.
+    pub fn violations(&self, metrics: &ProjectMetrics, granularity: Granularity) -> Vec<Violation> {
+        let reference = self.magnitude.unwrap_or_else(|| mean_magnitude(metrics));
+
+        let mut violations = vec![];
+        for file in metrics.file_metrics() {
+            match granularity {
+                Granularity::File => self.check(
+                    file,
+                    None,
+                    file.complexity,
+                    &Rule::ALL,
+                    reference,
+                    &mut violations,
+                ),
+                Granularity::Function => {
+                    // Functions share the churn of their file, which is checked once
+                    self.check(
+                        file,
+                        None,
+                        file.complexity,
+                        &[Rule::Churn],
+                        reference,
+                        &mut violations,
+                    );
+                    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+                    for function in &file.functions {
+                        let occurrence = occurrences.entry(&function.name).or_default();
+                        self.check(
+                            file,
+                            Some((function, *occurrence)),
+                            function.cyclomatic,
+                            &[Rule::Complexity, Rule::Magnitude],
+                            reference,
+                            &mut violations,
+                        );
+                        *occurrence += 1;
+                    }
+                }
+            }
+        }
+        violations
+    }
+
+    fn check(
+        &self,
+        file: &FileMetrics,
+        function: Option<(&FunctionMetrics, usize)>,
+        complexity: f64,
+        rules: &[Rule],
+        reference: f64,
+        violations: &mut Vec<Violation>,
+    ) {
+        let churn = file.churn.as_f64();
+        let magnitude = (churn.powi(2) + complexity.powi(2)).sqrt();
+
+        for &rule in rules {
+            let (value, threshold) = match rule {
+                Rule::Complexity => (complexity, self.complexity),
+                Rule::Churn => (churn, self.churn.map(Churn::as_f64)),
+                Rule::Magnitude => (magnitude, self.magnitude),
+            };
+            let Some(threshold) = threshold else {
+                continue;
+            };
+            if value > threshold {
+                violations.push(Violation {
+                    filename: file.filename.clone(),
+                    function: function.map(|(function, _)| function.clone()),
+                    occurrence: function.map_or(0, |(_, occurrence)| occurrence),
+                    rule,
+                    value,
+                    threshold,
+                    magnitude,
+                    severity: Severity::from_magnitude(magnitude, reference),
+                });
+            }
+        }
+    }
+}
+
+fn mean_magnitude(metrics: &ProjectMetrics) -> f64 {
+    let files = metrics.file_metrics();
+    if files.is_empty() {
+        return 0.0;
+    }
+    files.iter().map(FileMetrics::magnitude).sum::<f64>() / files.len() as f64
+}
+
+/// 64-bit FNV-1a, unlike the std hasher its output is stable across Rust releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::{Churn, FileMetrics, FunctionMetrics, ProjectMetrics};
+
+    use super::{Granularity, Rule, Severity, Thresholds};
+
+    fn metrics() -> ProjectMetrics {
+        let mut file = FileMetrics::new("src/foo.rs".to_string(), Churn::from(4), 30.0);
+        file.functions = vec![
+            FunctionMetrics::new("simple", 1, 3, 1.0),
+            FunctionMetrics::new("tangled", 5, 40, 25.0),
+        ];
+        ProjectMetrics::new(vec![
+            file,
+            FileMetrics::new("src/bar.rs".to_string(), Churn::from(1), 2.0),
+        ])
+    }
+
+    #[test]
+    fn file_violations_per_rule() {
+        let thresholds = Thresholds {
+            complexity: Some(10.0),
+            churn: Some(Churn::from(3)),
+            magnitude: None,
+        };
+
+        let violations = thresholds.violations(&metrics(), Granularity::File);
+
+        let rules: Vec<_> = violations.iter().map(|v| v.rule).collect();
+        assert_eq!(rules, vec![Rule::Complexity, Rule::Churn]);
+        assert!(violations.iter().all(|v| v.filename == "src/foo.rs"));
+        assert_eq!(violations[0].start_line(), 1);
+    }
+
+    #[test]
+    fn function_violations_have_a_location() {
+        let thresholds = Thresholds {
+            complexity: Some(10.0),
+            churn: Some(Churn::from(3)),
+            ..Thresholds::default()
+        };
+
+        let violations = thresholds.violations(&metrics(), Granularity::Function);
+
+        assert_eq!(violations.len(), 2);
+        let churn: Vec<_> = violations
+            .iter()
+            .filter(|v| v.rule == Rule::Churn)
+            .collect();
+        assert_eq!(churn.len(), 1);
+        assert_eq!(churn[0].function, None);
+        let complexity = &violations[1];
+        assert_eq!(complexity.rule, Rule::Complexity);
+        assert_eq!(complexity.start_line(), 5);
+        assert_eq!(complexity.end_line(), Some(40));
+        assert!(complexity.message().contains("`tangled`"));
+    }
+
+    #[test]
+    fn fingerprint_ignores_values() {
+        let thresholds = Thresholds {
+            complexity: Some(10.0),
+            ..Thresholds::default()
+        };
+        let mut changed = metrics();
+        let mut file = changed.file_metrics()[0].clone();
+        file.complexity = 50.0;
+        changed = ProjectMetrics::new(vec![file]);
+
+        let before = thresholds.violations(&metrics(), Granularity::File);
+        let after = thresholds.violations(&changed, Granularity::File);
+
+        assert_eq!(before[0].fingerprint(), after[0].fingerprint());
+        assert_eq!(before[0].fingerprint().len(), 16);
+    }
+
+    #[test]
+    fn fingerprint_tells_same_named_functions_apart() {
+        let thresholds = Thresholds {
+            complexity: Some(10.0),
+            ..Thresholds::default()
+        };
+        let mut file = FileMetrics::new("src/display.rs".to_string(), Churn::from(2), 40.0);
+        file.functions = vec![
+            FunctionMetrics::new("fmt", 3, 20, 12.0),
+            FunctionMetrics::new("fmt", 25, 50, 15.0),
+        ];
+
+        let violations =
+            thresholds.violations(&ProjectMetrics::new(vec![file]), Granularity::Function);
+
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[1].occurrence, 1);
+        assert_ne!(violations[0].fingerprint(), violations[1].fingerprint());
+    }
+
+    #[test]
+    fn severity_from_magnitude() {
+        assert_eq!(Severity::from_magnitude(5.0, 10.0), Severity::Note);
+        assert_eq!(Severity::from_magnitude(10.0, 10.0), Severity::Warning);
+        assert_eq!(Severity::from_magnitude(25.0, 10.0), Severity::Error);
+    }
+}
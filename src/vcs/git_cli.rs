@@ -0,0 +1,209 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Context, Result};
+
+use crate::{
+    git::{reachable_from, CommitRecord, RepositoryExplorer},
+    metrics::{BlamedLine, Churn},
+    pattern::wildcard_match,
+};
+
+use super::{run, split};
+
+/// Explorer running the `git` command line
+pub struct GitCli {
+    root: PathBuf,
+}
+
+impl GitCli {
+    pub fn try_new(path: &Path) -> Result<Self> {
+        let root = run("git", path, &["rev-parse", "--show-toplevel"])
+            .wrap_err("Git repository not found")?;
+        let root = String::from_utf8_lossy(&root).trim().to_string();
+        Ok(Self {
+            root: PathBuf::from(root),
+        })
+    }
+
+    fn git(&self, args: &[&str]) -> Result<Vec<u8>> {
+        let mut all_args = vec!["-c", "core.quotePath=false"];
+        all_args.extend(args);
+        run("git", &self.root, &all_args)
+    }
+
+    /// Commits reachable from any of `revisions`, newest first
+    fn log(&self, revisions: &[&str]) -> Result<Vec<CommitRecord>> {
+        // Each commit is `\x01<id> <parents>\x03<author>\x03<email>\x03<time>\x02<message>\x02`
+        // followed by its files
+        let mut args = vec![
+            "log",
+            "--format=%x01%H %P%x03%an%x03%ae%x03%at%x02%B%x02",
+            "--name-only",
+            "--no-renames",
+            "-z",
+        ];
+        args.extend(revisions);
+        args.push("--");
+        let log = self.git(&args)?;
+
+        let mut commits = vec![];
+        for entry in split(&log, 0x01) {
+            let mut parts = entry.splitn(3, '\x02');
+            let mut header = parts.next().unwrap_or_default().split('\x03');
+            let mut ids = header.next().unwrap_or_default().split(' ');
+            let id = ids.next().unwrap_or_default().to_string();
+            let parents: Vec<_> = ids
+                .filter(|parent| !parent.is_empty())
+                .map(str::to_string)
+                .collect();
+            let author = header.next().unwrap_or_default().to_string();
+            let email = header.next().unwrap_or_default().to_string();
+            let time = header
+                .next()
+                .unwrap_or_default()
+                .parse()
+                .unwrap_or_default();
+            let message = parts.next().unwrap_or_default().trim_end().to_string();
+            let files = parts
+                .next()
+                .unwrap_or_default()
+                .split('\0')
+                .map(|file| file.trim_matches('\n'))
+                .filter(|file| !file.is_empty())
+                .map(str::to_string)
+                .collect();
+            commits.push(CommitRecord {
+                id,
+                message,
+                author,
+                email,
+                time,
+                is_merge: parents.len() > 1,
+                parents,
+                files,
+            });
+        }
+        Ok(commits)
+    }
+}
+
+impl RepositoryExplorer for GitCli {
+    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+        self.change_count_per_file_until("HEAD")
+    }
+
+    fn commits(&self, revision: &str) -> Result<Vec<CommitRecord>> {
+        self.log(&[revision])
+    }
+
+    fn commits_from(&self, revisions: &[String]) -> Result<Vec<(CommitRecord, Vec<usize>)>> {
+        let commits: Vec<_> = revisions
+            .iter()
+            .map(|revision| format!("{revision}^{{commit}}"))
+            .collect();
+        let mut args = vec!["rev-parse"];
+        args.extend(commits.iter().map(String::as_str));
+        let tips = split(&self.git(&args)?, b'\n');
+        let revisions: Vec<_> = revisions.iter().map(String::as_str).collect();
+        Ok(reachable_from(&tips, self.log(&revisions)?))
+    }
+
+    fn references(&self, pattern: &str) -> Result<Vec<String>> {
+        let references = self.git(&["for-each-ref", "--format=%(refname)"])?;
+        let mut names: Vec<_> = split(&references, b'\n')
+            .into_iter()
+            .filter(|name| wildcard_match(pattern, name))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn merge_base(&self, base: &str, head: &str) -> Result<String> {
+        let id = self.git(&["merge-base", base, head])?;
+        Ok(String::from_utf8_lossy(&id).trim().to_string())
+    }
+
+    fn changed_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        let diff = self.git(&["diff", "--name-only", "--no-renames", "-z", from, to, "--"])?;
+        let mut files = split(&diff, b'\0');
+        files.sort();
+        Ok(files)
+    }
+
+    fn file_content(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>> {
+        let commit = format!("{revision}^{{commit}}");
+        self.git(&["rev-parse", "--verify", "--quiet", &commit])
+            .wrap_err(format!("Unable to find revision {revision}"))?;
+
+        match self.git(&["cat-file", "blob", &format!("{revision}:{path}")]) {
+            Ok(content) => Ok(Some(content)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn blame(&self, revision: &str, path: &str) -> Result<Vec<BlamedLine>> {
+        let blame = self.git(&["blame", "--line-porcelain", revision, "--", path])?;
+        let blame = String::from_utf8_lossy(&blame);
+
+        // Every line starts with the id of its commit, then repeats its `author` and
+        // `author-mail`, and ends with the content preceded by a tab
+        let mut lines = vec![];
+        let mut header = true;
+        let mut commit = "";
+        let mut author = "";
+        let mut email = "";
+        for line in blame.lines() {
+            if header {
+                commit = line.split(' ').next().unwrap_or_default();
+                header = false;
+            } else if line.starts_with('\t') {
+                lines.push(BlamedLine {
+                    commit: commit.to_string(),
+                    author: author.to_string(),
+                    email: email.to_string(),
+                });
+                header = true;
+            } else if let Some(name) = line.strip_prefix("author ") {
+                author = name;
+            } else if let Some(mail) = line.strip_prefix("author-mail ") {
+                email = mail.trim_start_matches('<').trim_end_matches('>');
+            }
+        }
+        Ok(lines)
+    }
+
+    fn worktree_changes(&self) -> Result<Vec<String>> {
+        let diff = self.git(&["diff", "--name-only", "--no-renames", "-z", "HEAD", "--"])?;
+        let mut files = split(&diff, b'\0');
+        files.sort();
+        Ok(files)
+    }
+
+    fn shallow_depth(&self) -> Result<Option<usize>> {
+        let shallow = self.git(&["rev-parse", "--is-shallow-repository"])?;
+        if String::from_utf8_lossy(&shallow).trim() != "true" {
+            return Ok(None);
+        }
+        let count = self.git(&["rev-list", "--count", "HEAD"])?;
+        let count = String::from_utf8_lossy(&count)
+            .trim()
+            .parse()
+            .wrap_err("Unable to count the commits of HEAD")?;
+        Ok(Some(count))
+    }
+
+    fn submodules(&self) -> Result<Vec<(String, PathBuf)>> {
+        let tree = self.git(&["ls-tree", "-r", "-z", "HEAD"])?;
+        Ok(split(&tree, b'\0')
+            .into_iter()
+            .filter_map(|entry| {
+                let (info, path) = entry.split_once('\t')?;
+                info.starts_with("160000 commit ")
+                    .then(|| (path.to_string(), self.root.join(path)))
+            })
+            .collect())
+    }
+}
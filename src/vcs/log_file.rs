@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+use eyre::{eyre, Context, Result};
+use flate2::read::GzDecoder;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{git::RepositoryExplorer, metrics::Churn};
+
+/// Explorer reading the history from an exported log, and optionally the files from a source archive.
+///
+/// Both describe a single snapshot, which is available as the `HEAD` revision.
+#[derive(Debug, Default)]
+pub struct LogFile {
+    changes: HashMap<String, Churn>,
+    sources: Option<HashMap<String, Vec<u8>>>,
+}
+
+/// One commit of a JSON export
+#[derive(Debug, Deserialize)]
+struct ExportedCommit {
+    files: Vec<ExportedFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ExportedFile {
+    Path(String),
+    Entry { path: String },
+}
+
+impl LogFile {
+    /// Read a `git log --numstat` text export, or a JSON export, told apart by their content
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .wrap_err(format!("Unable to read log file {}", path.display()))?;
+
+        if content.trim_start().starts_with('[') {
+            Self::from_json(&content)
+        } else {
+            Ok(Self::from_numstat(&content))
+        }
+    }
+
+    /// Count the `added<TAB>deleted<TAB>path` lines of a `git log --numstat` output, whatever
+    /// the `--format` of the commit lines.
+    pub fn from_numstat(content: &str) -> Self {
+        let numstat = Regex::new(r"^(?:\d+|-)\t(?:\d+|-)\t(.+)$").expect("valid regex");
+
+        let mut log = Self::default();
+        for line in content.lines() {
+            if let Some(path) = numstat.captures(line).and_then(|c| c.get(1)) {
+                log.add(renamed_path(path.as_str()));
+            }
+        }
+        log
+    }
+
+    /// Read an array of commits, each with the `files` it changed as paths or `{"path": ...}` objects
+    pub fn from_json(content: &str) -> Result<Self> {
+        let commits: Vec<ExportedCommit> =
+            serde_json::from_str(content).wrap_err("Invalid JSON log export")?;
+
+        let mut log = Self::default();
+        for file in commits.into_iter().flat_map(|commit| commit.files) {
+            match file {
+                ExportedFile::Path(path) | ExportedFile::Entry { path } => log.add(path),
+            }
+        }
+        Ok(log)
+    }
+
+    /// Read the files from a tar archive, gzipped or not, such as one made by `git archive`.
+    ///
+    /// A top directory shared by every file is dropped from their paths when the paths of the log
+    /// match them better without it.
+    pub fn with_archive(mut self, path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).wrap_err(format!("Unable to open archive {}", path.display()))?;
+        let is_gzip = path
+            .extension()
+            .is_some_and(|extension| extension == "gz" || extension == "tgz");
+        let reader: Box<dyn Read> = if is_gzip {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+
+        let mut sources = HashMap::new();
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries().wrap_err("Unable to read the archive")? {
+            let mut entry = entry.wrap_err("Unable to read an archive entry")?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry
+                .path()
+                .wrap_err("Invalid path in the archive")?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let mut content = vec![];
+            entry
+                .read_to_end(&mut content)
+                .wrap_err(format!("Unable to read {name} from the archive"))?;
+            sources.insert(name, content);
+        }
+
+        self.sources = Some(strip_top_directory(sources, &self.changes));
+        Ok(self)
+    }
+
+    fn add(&mut self, path: String) {
+        *self.changes.entry(path).or_default() += 1;
+    }
+
+    fn check_revision(revision: &str) -> Result<()> {
+        if revision == "HEAD" {
+            Ok(())
+        } else {
+            Err(eyre!(
+                "a log file only describes its exported snapshot, not {revision}"
+            ))
+        }
+    }
+}
+
+/// Path after a rename, written `old => new` or `dir/{old => new}/file` by git
+fn renamed_path(path: &str) -> String {
+    if let (Some(open), Some(close)) = (path.find('{'), path.rfind('}')) {
+        if let Some((_, new)) = path[open + 1..close].split_once(" => ") {
+            let joined = format!("{}{new}{}", &path[..open], &path[close + 1..]);
+            return joined.replace("//", "/");
+        }
+    }
+    match path.split_once(" => ") {
+        Some((_, new)) => new.to_string(),
+        None => path.to_string(),
+    }
+}
+
+/// Drop the top directory of the archive paths, like `project-1.0/`, when every path has it and
+/// more of the `changes` paths are found without it
+fn strip_top_directory(
+    sources: HashMap<String, Vec<u8>>,
+    changes: &HashMap<String, Churn>,
+) -> HashMap<String, Vec<u8>> {
+    let top = sources
+        .keys()
+        .next()
+        .and_then(|name| name.split_once('/'))
+        .map(|(top, _)| format!("{top}/"));
+    let Some(top) = top.filter(|top| sources.keys().all(|name| name.starts_with(top))) else {
+        return sources;
+    };
+    let matches = |strip: usize| {
+        sources
+            .keys()
+            .filter(|name| changes.contains_key(&name[strip..]))
+            .count()
+    };
+    if matches(top.len()) <= matches(0) {
+        return sources;
+    }
+    sources
+        .into_iter()
+        .map(|(name, content)| (name[top.len()..].to_string(), content))
+        .collect()
+}
+
+impl RepositoryExplorer for LogFile {
+    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+        Ok(self.changes.clone())
+    }
+
+    fn change_count_per_file_until(&self, revision: &str) -> Result<HashMap<String, Churn>> {
+        Self::check_revision(revision)?;
+        self.change_count_per_file()
+    }
+
+    fn file_content(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>> {
+        Self::check_revision(revision)?;
+        let sources = self
+            .sources
+            .as_ref()
+            .ok_or_else(|| eyre!("no source archive given with the log file"))?;
+        Ok(sources.get(path).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        path::{Path, PathBuf},
+    };
+
+    use tempfile::tempdir;
+
+    use crate::{Churn, RepositoryExplorer};
+
+    use super::{renamed_path, LogFile};
+
+    const NUMSTAT: &str = "commit 1111
+Author: Test <test@example.com>
+
+    Second
+
+5\t2\tsrc/lib.rs
+-\t-\tlogo.png
+3\t0\tsrc/{old => new}/mod.rs
+
+commit 0000
+Author: Test <test@example.com>
+
+    First
+
+10\t0\tsrc/lib.rs
+";
+
+    #[test]
+    fn count_numstat_lines() {
+        let log = LogFile::from_numstat(NUMSTAT);
+
+        let churn = log.change_count_per_file().expect("churn");
+        assert_eq!(churn.len(), 3);
+        assert_eq!(churn["src/lib.rs"], Churn::from(2));
+        assert_eq!(churn["src/new/mod.rs"], Churn::from(1));
+    }
+
+    #[test]
+    fn count_json_files() {
+        let log = LogFile::from_json(
+            r#"[{"files": ["a.rs", {"path": "b.rs"}]}, {"id": "0000", "files": ["a.rs"]}]"#,
+        )
+        .expect("json log");
+
+        let churn = log.change_count_per_file().expect("churn");
+        assert_eq!(churn["a.rs"], Churn::from(2));
+        assert_eq!(churn["b.rs"], Churn::from(1));
+    }
+
+    #[test]
+    fn rename_notations() {
+        assert_eq!(renamed_path("a.rs => b.rs"), "b.rs");
+        assert_eq!(renamed_path("src/{a => b}/c.rs"), "src/b/c.rs");
+        assert_eq!(renamed_path("src/{ => b}/c.rs"), "src/b/c.rs");
+        assert_eq!(renamed_path("src/c.rs"), "src/c.rs");
+    }
+
+    /// Tar archive holding `content` at each of `paths`
+    fn archive(dir: &Path, paths: &[&str], content: &[u8]) -> PathBuf {
+        let archive_path = dir.join("sources.tar");
+        let mut builder = tar::Builder::new(File::create(&archive_path).expect("archive"));
+        for path in paths {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content)
+                .expect("entry added");
+        }
+        builder.finish().expect("archive written");
+        archive_path
+    }
+
+    #[test]
+    fn read_sources_from_archive() {
+        let dir = tempdir().expect("temp dir obtained");
+        let content = b"fn a() {}\n";
+        let archive_path = archive(dir.path(), &["project-1.0/src/a.rs"], content);
+
+        let log = LogFile::from_numstat("1\t0\tsrc/a.rs\n")
+            .with_archive(&archive_path)
+            .expect("archive read");
+
+        assert_eq!(
+            log.file_content("HEAD", "src/a.rs").expect("content"),
+            Some(content.to_vec())
+        );
+        assert!(log.file_content("v1.0", "src/a.rs").is_err());
+    }
+
+    #[test]
+    fn keep_a_top_directory_named_in_the_log() {
+        let dir = tempdir().expect("temp dir obtained");
+        let content = b"fn a() {}\n";
+        let archive_path = archive(dir.path(), &["src/a.rs", "src/b.rs"], content);
+
+        let log = LogFile::from_numstat("1\t0\tsrc/a.rs\n1\t0\tsrc/b.rs\n")
+            .with_archive(&archive_path)
+            .expect("archive read");
+
+        assert_eq!(
+            log.file_content("HEAD", "src/a.rs").expect("content"),
+            Some(content.to_vec())
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Context, Result};
+
+use crate::{
+    git::{reachable_from, CommitRecord, RepositoryExplorer},
+    metrics::{BlamedLine, Churn},
+    pattern::wildcard_match,
+};
+
+use super::{run, split};
+
+/// Explorer running the `hg` command line on Mercurial repositories
+pub struct Mercurial {
+    root: PathBuf,
+}
+
+impl Mercurial {
+    pub fn try_new(path: &Path) -> Result<Self> {
+        let root = run("hg", path, &["root"]).wrap_err("Mercurial repository not found")?;
+        let root = String::from_utf8_lossy(&root).trim().to_string();
+        Ok(Self {
+            root: PathBuf::from(root),
+        })
+    }
+
+    fn hg(&self, args: &[&str]) -> Result<Vec<u8>> {
+        let mut all_args = vec!["--noninteractive", "--config", "ui.color=never"];
+        all_args.extend(args);
+        run("hg", &self.root, &all_args)
+    }
+
+    /// Commits reachable from any of the revisions `names`, newest first
+    fn log(&self, names: &[&str]) -> Result<Vec<CommitRecord>> {
+        let revisions: Vec<_> = names.iter().map(|name| revision(name)).collect();
+        let revset = format!("reverse(::({}))", revisions.join(" + "));
+        let log = self.hg(&[
+            "log",
+            "-r",
+            &revset,
+            "--template",
+            "\x01{node} {p1node} {p2node}\x03{author|person}\x03{author|email}\x03{date|hgdate}\x02{desc}\x02{files % '{file}\n'}",
+        ])?;
+
+        let mut commits = vec![];
+        for entry in split(&log, 0x01) {
+            let mut parts = entry.splitn(3, '\x02');
+            let mut header = parts.next().unwrap_or_default().split('\x03');
+            let mut ids = header.next().unwrap_or_default().split(' ');
+            let id = ids.next().unwrap_or_default();
+            // Missing parents are written as the null id
+            let parents: Vec<_> = ids
+                .filter(|parent| parent.chars().any(|c| c != '0'))
+                .map(str::to_string)
+                .collect();
+            let author = header.next().unwrap_or_default().to_string();
+            let email = header.next().unwrap_or_default().to_string();
+            // `hgdate` is the Unix time followed by the timezone offset
+            let time = header
+                .next()
+                .and_then(|date| date.split(' ').next())
+                .and_then(|seconds| seconds.parse().ok())
+                .unwrap_or_default();
+            let message = parts.next().unwrap_or_default().to_string();
+            let lines = parts.next().unwrap_or_default().lines();
+            // Like `git log`, merges do not list the files they change
+            let is_merge = parents.len() > 1;
+            commits.push(CommitRecord {
+                id: id.to_string(),
+                message,
+                author,
+                email,
+                time,
+                is_merge,
+                parents,
+                files: if is_merge {
+                    vec![]
+                } else {
+                    lines.map(str::to_string).collect()
+                },
+            });
+        }
+        Ok(commits)
+    }
+}
+
+/// Mercurial name of a revision, `HEAD` being the parent of the working directory and
+/// `refs/heads/<name>` a branch or bookmark, as references are listed
+fn revision(name: &str) -> &str {
+    if name == "HEAD" {
+        "."
+    } else {
+        name.strip_prefix("refs/heads/").unwrap_or(name)
+    }
+}
+
+impl RepositoryExplorer for Mercurial {
+    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+        self.change_count_per_file_until("HEAD")
+    }
+
+    fn commits(&self, name: &str) -> Result<Vec<CommitRecord>> {
+        self.log(&[name])
+    }
+
+    fn commits_from(&self, names: &[String]) -> Result<Vec<(CommitRecord, Vec<usize>)>> {
+        let mut tips = vec![];
+        for name in names {
+            let tip = self.hg(&["log", "-r", revision(name), "--template", "{node}"])?;
+            tips.push(String::from_utf8_lossy(&tip).trim().to_string());
+        }
+        let names: Vec<_> = names.iter().map(String::as_str).collect();
+        Ok(reachable_from(&tips, self.log(&names)?))
+    }
+
+    /// Named branches and bookmarks matching `pattern`, both named `refs/heads/<name>` like git
+    /// branches
+    fn references(&self, pattern: &str) -> Result<Vec<String>> {
+        let branches = self.hg(&["branches", "--template", "{branch}\n"])?;
+        let bookmarks = self.hg(&["bookmarks", "--template", "{bookmark}\n"])?;
+        let mut names: Vec<_> = split(&branches, b'\n')
+            .into_iter()
+            .chain(split(&bookmarks, b'\n'))
+            .map(|name| format!("refs/heads/{name}"))
+            .filter(|name| wildcard_match(pattern, name))
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    fn merge_base(&self, base: &str, head: &str) -> Result<String> {
+        let revset = format!("ancestor({}, {})", revision(base), revision(head));
+        let id = self.hg(&["log", "-r", &revset, "--template", "{node}"])?;
+        Ok(String::from_utf8_lossy(&id).trim().to_string())
+    }
+
+    fn changed_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        let status = self.hg(&[
+            "status",
+            "--rev",
+            revision(from),
+            "--rev",
+            revision(to),
+            "--modified",
+            "--added",
+            "--removed",
+            "--no-status",
+        ])?;
+        let mut files = split(&status, b'\n');
+        files.sort();
+        Ok(files)
+    }
+
+    fn file_content(&self, name: &str, path: &str) -> Result<Option<Vec<u8>>> {
+        self.hg(&["log", "-r", revision(name), "--template", "{node}"])
+            .wrap_err(format!("Unable to find revision {name}"))?;
+
+        match self.hg(&["cat", "-r", revision(name), path]) {
+            Ok(content) => Ok(Some(content)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn blame(&self, name: &str, path: &str) -> Result<Vec<BlamedLine>> {
+        let annotate = self.hg(&[
+            "annotate",
+            "-r",
+            revision(name),
+            "--template",
+            "{lines % '{node} {user}\n'}",
+            path,
+        ])?;
+
+        // Users are written `Name <email>`
+        let lines = split(&annotate, b'\n')
+            .into_iter()
+            .map(|line| {
+                let (commit, user) = line.split_once(' ').unwrap_or((&line, ""));
+                let (author, email) = match user.split_once(" <") {
+                    Some((author, email)) => (author, email.trim_end_matches('>')),
+                    None => (user, ""),
+                };
+                BlamedLine {
+                    commit: commit.to_string(),
+                    author: author.to_string(),
+                    email: email.to_string(),
+                }
+            })
+            .collect();
+        Ok(lines)
+    }
+
+    fn worktree_changes(&self) -> Result<Vec<String>> {
+        let status = self.hg(&[
+            "status",
+            "--modified",
+            "--added",
+            "--removed",
+            "--no-status",
+        ])?;
+        let mut files = split(&status, b'\n');
+        files.sort();
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, process::Command};
+
+    use crate::RepositoryExplorer;
+
+    use super::{revision, Mercurial};
+
+    fn hg(directory: &Path, args: &[&str]) -> bool {
+        Command::new("hg")
+            .args(["--config", "ui.username=Test <test@example.com>"])
+            .args(args)
+            .current_dir(directory)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    #[test]
+    fn head_is_the_working_directory_parent() {
+        assert_eq!(revision("HEAD"), ".");
+        assert_eq!(revision("default"), "default");
+        assert_eq!(revision("refs/heads/stable"), "stable");
+    }
+
+    #[test]
+    #[ignore = "needs the hg command line, run with `cargo test -- --ignored`"]
+    fn mercurial_count_changes() {
+        let dir = tempfile::tempdir().expect("temp dir obtained");
+        let root = dir.path();
+        assert!(hg(root, &["init"]), "hg is not installed");
+        fs::write(root.join("a.rs"), "fn a() {}\n").expect("file written");
+        assert!(hg(root, &["commit", "-A", "-m", "first"]));
+        fs::write(root.join("a.rs"), "fn a() { if true {} }\n").expect("file written");
+        assert!(hg(root, &["commit", "-m", "second"]));
+
+        let explorer = Mercurial::try_new(root).expect("explorer");
+
+        let churn = explorer.change_count_per_file().expect("churn");
+        assert_eq!(churn.get("a.rs").map(|c| c.as_f64()), Some(2.0));
+        assert!(explorer
+            .file_content("HEAD", "a.rs")
+            .expect("content")
+            .is_some());
+        let lines = explorer.blame("HEAD", "a.rs").expect("blame");
+        assert_eq!(lines[0].email, "test@example.com");
+    }
+}
@@ -0,0 +1,599 @@
+use std::{
+    cell::OnceCell, cmp::Reverse, collections::HashMap, path::Path, path::PathBuf, process::Command,
+};
+
+use clap::ValueEnum;
+use eyre::{eyre, Context, Result};
+
+use crate::git::{CommitRecord, Gitoxide, ReadFailure, RepositoryExplorer};
+use crate::metrics::{BlamedLine, Churn};
+
+pub use self::git_cli::GitCli;
+pub use self::log_file::LogFile;
+pub use self::mercurial::Mercurial;
+
+mod git_cli;
+mod log_file;
+mod mercurial;
+
+/// Version control backend used to read the history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Vcs {
+    /// Gitoxide, then the git command line, then Mercurial, whichever opens the repository first.
+    /// Gitoxide also hands over to the git command line the first time it fails to read it
+    #[default]
+    Auto,
+    /// Read git repositories in process with gitoxide
+    Gitoxide,
+    /// Run the `git` command line, for repositories gitoxide cannot read
+    Git,
+    /// Run the `hg` command line on Mercurial repositories
+    Hg,
+}
+
+/// Open the repository holding `path` with the chosen backend
+pub fn open_explorer(path: PathBuf, vcs: Vcs) -> Result<Box<dyn RepositoryExplorer>> {
+    match vcs {
+        Vcs::Gitoxide => Ok(Box::new(Gitoxide::try_new(path)?)),
+        Vcs::Git => Ok(Box::new(GitCli::try_new(&path)?)),
+        Vcs::Hg => Ok(Box::new(Mercurial::try_new(&path)?)),
+        Vcs::Auto => {
+            if let Ok(explorer) = Gitoxide::try_new(path.clone()) {
+                return Ok(Box::new(Fallback::new(explorer, path)));
+            }
+            if let Ok(explorer) = GitCli::try_new(&path) {
+                return Ok(Box::new(explorer));
+            }
+            if let Ok(explorer) = Mercurial::try_new(&path) {
+                return Ok(Box::new(explorer));
+            }
+            Err(eyre!(
+                "No git or Mercurial repository found at {}",
+                path.display()
+            ))
+        }
+    }
+}
+
+/// Explorer switching to the git command line for good the first time `primary` fails to read
+/// the history or objects, as gitoxide may open repositories it cannot read. Other errors, such
+/// as unknown revisions, are returned as they are
+struct Fallback<E> {
+    primary: E,
+    path: PathBuf,
+    git: OnceCell<GitCli>,
+}
+
+impl<E: RepositoryExplorer> Fallback<E> {
+    fn new(primary: E, path: PathBuf) -> Self {
+        Self {
+            primary,
+            path,
+            git: OnceCell::new(),
+        }
+    }
+
+    fn read<T>(&self, read: impl Fn(&dyn RepositoryExplorer) -> Result<T>) -> Result<T> {
+        if let Some(git) = self.git.get() {
+            return read(git);
+        }
+        let error = match read(&self.primary) {
+            Ok(value) => return Ok(value),
+            Err(error) if error.downcast_ref::<ReadFailure>().is_some() => error,
+            Err(error) => return Err(error),
+        };
+        let Ok(git) = GitCli::try_new(&self.path) else {
+            return Err(error);
+        };
+        eprintln!("warning: {error:#}, reading the repository with the git command line instead");
+        read(self.git.get_or_init(|| git))
+    }
+}
+
+impl<E: RepositoryExplorer> RepositoryExplorer for Fallback<E> {
+    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+        self.read(|explorer| explorer.change_count_per_file())
+    }
+
+    fn change_count_per_file_until(&self, revision: &str) -> Result<HashMap<String, Churn>> {
+        self.read(|explorer| explorer.change_count_per_file_until(revision))
+    }
+
+    fn commits(&self, revision: &str) -> Result<Vec<CommitRecord>> {
+        self.read(|explorer| explorer.commits(revision))
+    }
+
+    fn commits_from(&self, revisions: &[String]) -> Result<Vec<(CommitRecord, Vec<usize>)>> {
+        self.read(|explorer| explorer.commits_from(revisions))
+    }
+
+    fn references(&self, pattern: &str) -> Result<Vec<String>> {
+        self.read(|explorer| explorer.references(pattern))
+    }
+
+    fn merge_base(&self, base: &str, head: &str) -> Result<String> {
+        self.read(|explorer| explorer.merge_base(base, head))
+    }
+
+    fn changed_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        self.read(|explorer| explorer.changed_files(from, to))
+    }
+
+    fn file_content(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>> {
+        self.read(|explorer| explorer.file_content(revision, path))
+    }
+
+    fn blame(&self, revision: &str, path: &str) -> Result<Vec<BlamedLine>> {
+        self.read(|explorer| explorer.blame(revision, path))
+    }
+
+    fn worktree_changes(&self) -> Result<Vec<String>> {
+        self.read(|explorer| explorer.worktree_changes())
+    }
+
+    fn shallow_depth(&self) -> Result<Option<usize>> {
+        self.read(|explorer| explorer.shallow_depth())
+    }
+
+    fn submodules(&self) -> Result<Vec<(String, PathBuf)>> {
+        self.read(|explorer| explorer.submodules())
+    }
+}
+
+/// Explorer adding the history of the checked out submodules, their files prefixed with their path
+pub struct WithSubmodules {
+    repository: Box<dyn RepositoryExplorer>,
+    submodules: Vec<(String, WithSubmodules)>,
+    skipped: Vec<String>,
+}
+
+impl WithSubmodules {
+    /// Open the submodules of `repository`, and theirs, with the `vcs` backend
+    pub fn open(repository: Box<dyn RepositoryExplorer>, vcs: Vcs) -> Result<Self> {
+        let mut submodules = vec![];
+        let mut skipped = vec![];
+        for (path, directory) in repository.submodules()? {
+            // Without its own `.git`, opening a submodule would find the parent repository
+            if !directory.join(".git").exists() {
+                skipped.push(path);
+                continue;
+            }
+            let explorer = open_explorer(directory, vcs)
+                .wrap_err(format!("Unable to open submodule {path}"))?;
+            let nested = Self::open(explorer, vcs)?;
+            skipped.extend(nested.skipped.iter().map(|name| format!("{path}/{name}")));
+            submodules.push((path, nested));
+        }
+        Ok(Self {
+            repository,
+            submodules,
+            skipped,
+        })
+    }
+
+    /// Submodules left out because they are not checked out
+    pub fn skipped(&self) -> &[String] {
+        &self.skipped
+    }
+
+    /// Submodule histories are only read at `HEAD`, their revisions being unrelated to the parent ones
+    fn check_revision(revision: &str) -> Result<()> {
+        if revision == "HEAD" {
+            Ok(())
+        } else {
+            Err(eyre!(
+                "submodule histories are only read at HEAD, not at {revision}"
+            ))
+        }
+    }
+
+    fn submodule_of<'a>(&self, path: &'a str) -> Option<(&WithSubmodules, &'a str)> {
+        self.submodules.iter().find_map(|(prefix, submodule)| {
+            let rest = path.strip_prefix(prefix.as_str())?.strip_prefix('/')?;
+            Some((submodule, rest))
+        })
+    }
+}
+
+impl RepositoryExplorer for WithSubmodules {
+    fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+        let mut change_map = self.repository.change_count_per_file()?;
+        for (prefix, submodule) in &self.submodules {
+            let files = submodule.change_count_per_file()?.into_iter();
+            change_map.extend(files.map(|(file, churn)| (format!("{prefix}/{file}"), churn)));
+        }
+        Ok(change_map)
+    }
+
+    fn change_count_per_file_until(&self, revision: &str) -> Result<HashMap<String, Churn>> {
+        Self::check_revision(revision)?;
+        self.change_count_per_file()
+    }
+
+    /// Commits of the repository and of its submodules, newest first
+    fn commits(&self, revision: &str) -> Result<Vec<CommitRecord>> {
+        Self::check_revision(revision)?;
+        let mut commits = self.repository.commits(revision)?;
+        for (prefix, submodule) in &self.submodules {
+            commits.extend(submodule.commits(revision)?.into_iter().map(|mut commit| {
+                for file in commit.files.iter_mut() {
+                    *file = format!("{prefix}/{file}");
+                }
+                commit
+            }));
+        }
+        commits.sort_by_key(|commit| Reverse(commit.time));
+        Ok(commits)
+    }
+
+    /// Submodule files are only available at `HEAD`, their revisions being unrelated to the parent ones
+    fn file_content(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>> {
+        match self.submodule_of(path) {
+            Some((submodule, rest)) if revision == "HEAD" => submodule.file_content(revision, rest),
+            _ => self.repository.file_content(revision, path),
+        }
+    }
+
+    /// Like [`Self::file_content`], submodule files are only blamed at `HEAD`
+    fn blame(&self, revision: &str, path: &str) -> Result<Vec<BlamedLine>> {
+        match self.submodule_of(path) {
+            Some((submodule, rest)) if revision == "HEAD" => submodule.blame(revision, rest),
+            _ => self.repository.blame(revision, path),
+        }
+    }
+
+    fn worktree_changes(&self) -> Result<Vec<String>> {
+        let mut changes = self.repository.worktree_changes()?;
+        for (prefix, submodule) in &self.submodules {
+            let files = submodule.worktree_changes()?.into_iter();
+            changes.extend(files.map(|file| format!("{prefix}/{file}")));
+        }
+        changes.sort();
+        Ok(changes)
+    }
+
+    fn references(&self, pattern: &str) -> Result<Vec<String>> {
+        self.repository.references(pattern)
+    }
+
+    fn merge_base(&self, base: &str, head: &str) -> Result<String> {
+        self.repository.merge_base(base, head)
+    }
+
+    fn changed_files(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        self.repository.changed_files(from, to)
+    }
+
+    fn shallow_depth(&self) -> Result<Option<usize>> {
+        self.repository.shallow_depth()
+    }
+
+    fn submodules(&self) -> Result<Vec<(String, PathBuf)>> {
+        self.repository.submodules()
+    }
+}
+
+/// Standard output of `program` run in `directory`, an error with its standard error if it fails
+fn run(program: &str, directory: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(directory)
+        .output()
+        .wrap_err(format!("Unable to run {program}"))?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "{program} {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Non empty entries of a list separated by `separator`
+fn split(output: &[u8], separator: u8) -> Vec<String> {
+    output
+        .split(|byte| *byte == separator)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| String::from_utf8_lossy(entry).into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod fixture {
+    use std::{fs, path::Path, process::Command};
+
+    use tempfile::{tempdir, TempDir};
+
+    pub fn git(directory: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(["-c", "protocol.file.allow=always"])
+            .args(args)
+            .current_dir(directory)
+            .output()
+            .expect("git run")
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    /// Repository where `a.rs` changed twice and `b.rs` once, with `feature` one commit ahead of `main`
+    pub fn git_repository() -> TempDir {
+        let dir = tempdir().expect("temp dir obtained");
+        let root = dir.path();
+        git(root, &["init", "-q", "-b", "main"]);
+        fs::write(root.join("a.rs"), "fn a() {}\n").expect("file written");
+        fs::write(root.join("b.rs"), "fn b() {}\n").expect("file written");
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "first"]);
+        fs::write(root.join("a.rs"), "fn a() { if true {} }\n").expect("file written");
+        git(root, &["commit", "-q", "-am", "second"]);
+        git(root, &["checkout", "-q", "-b", "feature"]);
+        fs::write(root.join("c.rs"), "fn c() {}\n").expect("file written");
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "third"]);
+        git(root, &["checkout", "-q", "main"]);
+        dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        fs,
+    };
+
+    use eyre::{eyre, Result};
+
+    use crate::{
+        git::{CommitRecord, Gitoxide, ReadFailure},
+        Churn, RepositoryExplorer,
+    };
+
+    use crate::filter::{CommitFilter, Filtered};
+
+    use super::{fixture, open_explorer, Fallback, Vcs, WithSubmodules};
+
+    #[test]
+    fn backends_agree_on_git_repositories() {
+        let dir = fixture::git_repository();
+        let expected = HashMap::from([
+            ("a.rs".to_string(), Churn::from(2)),
+            ("b.rs".to_string(), Churn::from(1)),
+        ]);
+
+        for vcs in [Vcs::Auto, Vcs::Gitoxide, Vcs::Git] {
+            let explorer = open_explorer(dir.path().to_path_buf(), vcs).expect("explorer");
+
+            assert_eq!(explorer.change_count_per_file().expect("churn"), expected);
+            assert_eq!(
+                explorer.changed_files("main", "feature").expect("changes"),
+                vec!["c.rs"]
+            );
+            assert_eq!(
+                explorer.file_content("main", "c.rs").expect("content"),
+                None
+            );
+            assert!(explorer.merge_base("main", "feature").is_ok());
+            assert_eq!(
+                explorer.references("refs/heads/*").expect("references"),
+                vec!["refs/heads/feature", "refs/heads/main"]
+            );
+            let commits = explorer.commits("feature").expect("commits");
+            assert_eq!(commits.len(), 3);
+            assert_eq!(commits[0].parents, vec![commits[1].id.clone()]);
+            let branches = ["main".to_string(), "feature".to_string()];
+            let reached: BTreeMap<_, _> = explorer
+                .commits_from(&branches)
+                .expect("commits")
+                .into_iter()
+                .map(|(commit, reached)| (commit.message, reached))
+                .collect();
+            assert_eq!(
+                reached,
+                BTreeMap::from([
+                    ("first".to_string(), vec![0, 1]),
+                    ("second".to_string(), vec![0, 1]),
+                    ("third".to_string(), vec![1]),
+                ])
+            );
+            assert_eq!(commits[0].files, vec!["c.rs"]);
+            assert_eq!(commits[0].message, "third");
+            assert_eq!(commits[0].email, "test@example.com");
+            assert!(commits[0].time >= commits[2].time && commits[2].time > 0);
+            let lines = explorer.blame("HEAD", "a.rs").expect("blame");
+            assert_eq!(lines.len(), 1);
+            assert_eq!(lines[0].email, "test@example.com");
+            // The second commit last changed a.rs
+            assert_eq!(lines[0].commit, commits[1].id);
+        }
+    }
+
+    #[test]
+    fn merge_base_is_the_nearest_common_ancestor() {
+        let dir = tempfile::tempdir().expect("temp dir obtained");
+        let root = dir.path();
+        let commit = |file: &str, message: &str| {
+            fs::write(root.join(file), message).expect("file written");
+            fixture::git(root, &["add", "."]);
+            fixture::git(root, &["commit", "-q", "-m", message]);
+        };
+        fixture::git(root, &["init", "-q", "-b", "main"]);
+        commit("a.rs", "first");
+        fixture::git(root, &["checkout", "-q", "-b", "base"]);
+        commit("b.rs", "shared");
+        fixture::git(root, &["checkout", "-q", "-b", "topic"]);
+        commit("c.rs", "topic");
+        fixture::git(root, &["checkout", "-q", "base"]);
+        commit("b.rs", "base");
+        // `main` merges `topic` into the first commit, which it reaches before `shared`
+        fixture::git(root, &["checkout", "-q", "main"]);
+        fixture::git(root, &["merge", "-q", "--no-ff", "topic", "-m", "merge"]);
+
+        for vcs in [Vcs::Gitoxide, Vcs::Git] {
+            let explorer = open_explorer(root.to_path_buf(), vcs).expect("explorer");
+            let shared = &explorer.commits("base").expect("commits")[1];
+
+            assert_eq!(shared.message, "shared");
+            assert_eq!(
+                explorer.merge_base("base", "main").expect("merge base"),
+                shared.id
+            );
+        }
+    }
+
+    #[test]
+    fn worktree_changes_apply_filters() {
+        let dir = fixture::git_repository();
+        let root = dir.path();
+        fixture::git(root, &["config", "core.autocrlf", "true"]);
+        fs::write(root.join("a.rs"), "fn a() { if true {} }\r\n").expect("file written");
+        fs::write(root.join("b.rs"), "fn b() { loop {} }\n").expect("file written");
+
+        for vcs in [Vcs::Gitoxide, Vcs::Git] {
+            let explorer = open_explorer(root.to_path_buf(), vcs).expect("explorer");
+
+            assert_eq!(explorer.worktree_changes().expect("changes"), vec!["b.rs"]);
+        }
+    }
+
+    #[test]
+    fn detect_shallow_clones() {
+        let origin = fixture::git_repository();
+        let dir = tempfile::tempdir().expect("temp dir obtained");
+        let url = format!("file://{}", origin.path().display());
+        fixture::git(dir.path(), &["clone", "-q", "--depth", "1", &url, "clone"]);
+        let clone = dir.path().join("clone");
+
+        for vcs in [Vcs::Gitoxide, Vcs::Git] {
+            let full = open_explorer(origin.path().to_path_buf(), vcs).expect("explorer");
+            assert_eq!(full.shallow_depth().expect("depth"), None);
+
+            let shallow = open_explorer(clone.clone(), vcs).expect("explorer");
+            assert_eq!(shallow.shallow_depth().expect("depth"), Some(1));
+        }
+    }
+
+    #[test]
+    fn submodule_files_are_prefixed() {
+        let library = fixture::git_repository();
+        let project = fixture::git_repository();
+        let url = format!("file://{}", library.path().display());
+        fixture::git(project.path(), &["submodule", "add", "-q", &url, "lib"]);
+        fixture::git(project.path(), &["commit", "-q", "-m", "add lib"]);
+
+        for vcs in [Vcs::Gitoxide, Vcs::Git] {
+            let explorer = open_explorer(project.path().to_path_buf(), vcs).expect("explorer");
+            let explorer = WithSubmodules::open(explorer, vcs).expect("submodules");
+
+            let churn = explorer.change_count_per_file().expect("churn");
+            assert_eq!(churn.get("lib/a.rs"), Some(&Churn::from(2)));
+            assert_eq!(
+                explorer.change_count_per_file_until("HEAD").expect("churn"),
+                churn
+            );
+            assert!(explorer.change_count_per_file_until("main").is_err());
+            let commits = explorer.commits("HEAD").expect("commits");
+            assert_eq!(commits.len(), 5);
+            assert!(commits
+                .iter()
+                .any(|commit| commit.files == vec!["lib/a.rs".to_string()]));
+            assert_eq!(churn.get("a.rs"), Some(&Churn::from(2)));
+            assert!(explorer
+                .file_content("HEAD", "lib/b.rs")
+                .expect("content")
+                .is_some());
+            assert!(explorer.skipped().is_empty());
+        }
+    }
+
+    #[test]
+    fn filter_submodule_commits() {
+        let library = fixture::git_repository();
+        fs::write(library.path().join("a.rs"), "fn a() { loop {} }\n").expect("file written");
+        fixture::git(
+            library.path(),
+            &[
+                "-c",
+                "user.name=dependabot[bot]",
+                "commit",
+                "-q",
+                "-am",
+                "bump",
+            ],
+        );
+        let project = fixture::git_repository();
+        let url = format!("file://{}", library.path().display());
+        fixture::git(project.path(), &["submodule", "add", "-q", &url, "lib"]);
+        fixture::git(project.path(), &["commit", "-q", "-m", "add lib"]);
+
+        let explorer =
+            open_explorer(project.path().to_path_buf(), Vcs::Gitoxide).expect("explorer");
+        let explorer = WithSubmodules::open(explorer, Vcs::Gitoxide).expect("submodules");
+        let filter = CommitFilter {
+            authors: vec!["dependabot".to_string()],
+            ..CommitFilter::default()
+        };
+        let explorer = Filtered::new(explorer, filter);
+
+        let churn = explorer.change_count_per_file().expect("churn");
+        assert_eq!(churn.get("lib/a.rs"), Some(&Churn::from(2)));
+        assert_eq!(
+            explorer.log().summary().as_deref(),
+            Some("1 commits excluded: 1 by excluded authors")
+        );
+    }
+
+    /// Explorer opening any repository and reading none
+    struct Unreadable;
+
+    impl RepositoryExplorer for Unreadable {
+        fn change_count_per_file(&self) -> Result<HashMap<String, Churn>> {
+            Err(eyre!(ReadFailure(
+                "unsupported repository format".to_string()
+            )))
+        }
+
+        fn commits(&self, _revision: &str) -> Result<Vec<CommitRecord>> {
+            Err(eyre!(ReadFailure(
+                "unsupported repository format".to_string()
+            )))
+        }
+    }
+
+    #[test]
+    fn fall_back_to_git_when_history_fails() {
+        let dir = fixture::git_repository();
+
+        let explorer = Fallback::new(Unreadable, dir.path().to_path_buf());
+
+        assert_eq!(explorer.commits("HEAD").expect("commits").len(), 2);
+        assert!(explorer.git.get().is_some());
+        assert_eq!(
+            explorer.change_count_per_file().expect("churn").get("a.rs"),
+            Some(&Churn::from(2))
+        );
+    }
+
+    #[test]
+    fn keep_unknown_revision_errors() {
+        let dir = fixture::git_repository();
+        let gitoxide = Gitoxide::try_new(dir.path().to_path_buf()).expect("gitoxide");
+
+        let explorer = Fallback::new(gitoxide, dir.path().to_path_buf());
+
+        let error = explorer.commits("mistyped").expect_err("unknown revision");
+        assert!(error.to_string().contains("mistyped"));
+        assert!(explorer.git.get().is_none());
+        assert_eq!(explorer.commits("HEAD").expect("commits").len(), 2);
+    }
+
+    #[test]
+    fn auto_fails_outside_repositories() {
+        let dir = tempfile::tempdir().expect("temp dir obtained");
+
+        assert!(open_explorer(dir.path().to_path_buf(), Vcs::Auto).is_err());
+    }
+}
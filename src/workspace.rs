@@ -0,0 +1,411 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::{eyre, Context, Result};
+use serde::Deserialize;
+
+use crate::pattern::wildcard_match;
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    package: Option<PackageSection>,
+    workspace: Option<WorkspaceSection>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageSection {
+    name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WorkspaceSection {
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+/// A package of the analysed project and the directory it lives in, relative to the workspace root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    pub directory: PathBuf,
+}
+
+/// The packages declared by the root `Cargo.toml` of a project.
+#[derive(Debug, Clone, Default)]
+pub struct Workspace {
+    packages: Vec<Package>,
+}
+
+impl Workspace {
+    pub fn new(packages: Vec<Package>) -> Self {
+        Self { packages }
+    }
+
+    /// Read the root manifest of `root` and every workspace member manifest, with the package
+    /// directories relative to the repository holding `root`, like the analysed files.
+    ///
+    /// Returns `None` when the project has no `Cargo.toml`.
+    pub fn discover(root: &Path) -> Result<Option<Self>> {
+        let root = root
+            .canonicalize()
+            .wrap_err(format!("Unable to find {}", root.display()))?;
+        let repository = root
+            .ancestors()
+            .find(|directory| directory.join(".git").exists() || directory.join(".hg").exists())
+            .unwrap_or(&root);
+        let project: Vec<_> = root
+            .strip_prefix(repository)
+            .unwrap_or(Path::new(""))
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        Self::discover_in(&Directory(repository), &project.join("/"))
+    }
+
+    /// Like [`Workspace::discover`], with the manifests of a revision: `paths` are the files
+    /// it may hold and `read` gives the content of one of them, `None` when it is missing.
+    pub fn discover_at<'a>(
+        paths: impl IntoIterator<Item = &'a str>,
+        read: impl Fn(&str) -> Result<Option<Vec<u8>>>,
+    ) -> Result<Option<Self>> {
+        let mut directories = BTreeSet::new();
+        for path in paths {
+            let mut directory = path;
+            while let Some((parent, _)) = directory.rsplit_once('/') {
+                directories.insert(parent.to_string());
+                directory = parent;
+            }
+        }
+        Self::discover_in(&Revision { directories, read }, "")
+    }
+
+    /// Packages of the workspace in the `root` directory of `files`
+    fn discover_in(files: &impl Files, root: &str) -> Result<Option<Self>> {
+        let Some(manifest) = read_manifest(files, root)? else {
+            return Ok(None);
+        };
+        let mut packages = vec![];
+
+        if let Some(package) = manifest.package {
+            packages.push(Package {
+                name: package.name,
+                directory: PathBuf::from(root),
+            });
+        }
+
+        let workspace = manifest.workspace.unwrap_or_default();
+        for member in &workspace.members {
+            for directory in expand_member(files, root, member)? {
+                let is_excluded = workspace
+                    .exclude
+                    .iter()
+                    .any(|pattern| wildcard_match(pattern, &directory));
+                let directory = join(root, &directory);
+                let relative = PathBuf::from(&directory);
+                if is_excluded || packages.iter().any(|p| p.directory == relative) {
+                    continue;
+                }
+
+                let member_manifest = read_manifest(files, &directory)?;
+                if let Some(package) = member_manifest.and_then(|manifest| manifest.package) {
+                    packages.push(Package {
+                        name: package.name,
+                        directory: relative,
+                    });
+                }
+            }
+        }
+
+        Ok(Some(Self::new(packages)))
+    }
+
+    pub fn packages(&self) -> &[Package] {
+        &self.packages
+    }
+
+    /// Name of the package owning `filename`, found by the longest matching package directory.
+    pub fn package_for(&self, filename: &str) -> Option<&str> {
+        let path = Path::new(filename);
+        self.packages
+            .iter()
+            .filter(|package| path.starts_with(&package.directory))
+            .max_by_key(|package| package.directory.components().count())
+            .map(|package| package.name.as_str())
+    }
+
+    /// Fail like cargo does when a requested package is not part of the workspace.
+    pub fn check_packages(&self, names: &[String]) -> Result<()> {
+        for name in names {
+            if !self.packages.iter().any(|package| &package.name == name) {
+                return Err(eyre!(
+                    "package ID specification `{name}` did not match any packages"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Files the manifests are read from, by `/` separated paths relative to the project root
+trait Files {
+    /// Content of the file at `path`, `None` when it does not exist
+    fn read(&self, path: &str) -> Result<Option<String>>;
+
+    /// Names of the subdirectories of `directory`
+    fn subdirectories(&self, directory: &str) -> Result<Vec<String>>;
+}
+
+/// Files of the working tree under a root directory
+struct Directory<'a>(&'a Path);
+
+impl Files for Directory<'_> {
+    fn read(&self, path: &str) -> Result<Option<String>> {
+        let path = self.0.join(path);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        fs::read_to_string(&path)
+            .map(Some)
+            .wrap_err(format!("Unable to read manifest {}", path.display()))
+    }
+
+    fn subdirectories(&self, directory: &str) -> Result<Vec<String>> {
+        let path = self.0.join(directory);
+        let entries = fs::read_dir(&path).wrap_err(format!("Unable to list {}", path.display()))?;
+        Ok(entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect())
+    }
+}
+
+/// Files of a revision, read through a repository
+struct Revision<F> {
+    /// Every directory holding a file of the revision
+    directories: BTreeSet<String>,
+    read: F,
+}
+
+impl<F: Fn(&str) -> Result<Option<Vec<u8>>>> Files for Revision<F> {
+    fn read(&self, path: &str) -> Result<Option<String>> {
+        let content = (self.read)(path)?;
+        Ok(content.map(|content| String::from_utf8_lossy(&content).into_owned()))
+    }
+
+    fn subdirectories(&self, directory: &str) -> Result<Vec<String>> {
+        Ok(self
+            .directories
+            .iter()
+            .filter_map(|candidate| match directory {
+                "" => Some(candidate.as_str()),
+                _ => candidate.strip_prefix(directory)?.strip_prefix('/'),
+            })
+            .filter(|name| !name.contains('/'))
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+/// Manifest of the package or workspace in `directory`, `None` when it has none
+fn read_manifest(files: &impl Files, directory: &str) -> Result<Option<Manifest>> {
+    let path = join(directory, "Cargo.toml");
+    let Some(content) = files.read(&path)? else {
+        return Ok(None);
+    };
+    toml::from_str(&content)
+        .map(Some)
+        .wrap_err(format!("Unable to parse manifest {path}"))
+}
+
+/// Expand a workspace member entry of the workspace in `root`, which may contain wildcards in
+/// any path segment, to directories relative to `root`.
+fn expand_member(files: &impl Files, root: &str, member: &str) -> Result<Vec<String>> {
+    let mut candidates = vec![String::new()];
+
+    for segment in member.split('/').filter(|s| !s.is_empty() && *s != ".") {
+        let mut next = vec![];
+        for candidate in candidates {
+            if segment.contains(['*', '?']) {
+                for name in files.subdirectories(&join(root, &candidate))? {
+                    if wildcard_match(segment, &name) {
+                        next.push(join(&candidate, &name));
+                    }
+                }
+            } else {
+                next.push(join(&candidate, segment));
+            }
+        }
+        candidates = next;
+    }
+
+    candidates.sort();
+    let mut members = vec![];
+    for candidate in candidates {
+        if files
+            .read(&join(&join(root, &candidate), "Cargo.toml"))?
+            .is_some()
+        {
+            members.push(candidate);
+        }
+    }
+    Ok(members)
+}
+
+fn join(directory: &str, name: &str) -> String {
+    match directory {
+        "" => name.to_string(),
+        _ => format!("{directory}/{name}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs, path::PathBuf};
+
+    use tempfile::tempdir;
+
+    use super::{Package, Workspace};
+
+    fn write_manifest(dir: &std::path::Path, content: &str) {
+        fs::create_dir_all(dir).expect("directory created");
+        fs::write(dir.join("Cargo.toml"), content).expect("manifest written");
+    }
+
+    #[test]
+    fn package_for_uses_longest_directory() {
+        let workspace = Workspace::new(vec![
+            Package {
+                name: "root".to_string(),
+                directory: PathBuf::new(),
+            },
+            Package {
+                name: "core".to_string(),
+                directory: PathBuf::from("crates/core"),
+            },
+        ]);
+
+        assert_eq!(
+            workspace.package_for("crates/core/src/lib.rs"),
+            Some("core")
+        );
+        assert_eq!(workspace.package_for("src/main.rs"), Some("root"));
+    }
+
+    #[test]
+    fn unknown_package_is_an_error() {
+        let workspace = Workspace::new(vec![Package {
+            name: "core".to_string(),
+            directory: PathBuf::from("core"),
+        }]);
+
+        assert!(workspace.check_packages(&["core".to_string()]).is_ok());
+        assert!(workspace.check_packages(&["cli".to_string()]).is_err());
+    }
+
+    #[test]
+    fn discover_workspace_members() {
+        let dir = tempdir().expect("temp dir obtained");
+        write_manifest(
+            dir.path(),
+            r#"
+            [workspace]
+            members = ["crates/*", "tools/xtask"]
+            exclude = ["crates/ignored"]
+            "#,
+        );
+        write_manifest(
+            &dir.path().join("crates/core"),
+            "[package]\nname = \"my-core\"\n",
+        );
+        write_manifest(
+            &dir.path().join("crates/ignored"),
+            "[package]\nname = \"ignored\"\n",
+        );
+        write_manifest(
+            &dir.path().join("tools/xtask"),
+            "[package]\nname = \"xtask\"\n",
+        );
+
+        let workspace = Workspace::discover(dir.path())
+            .expect("manifest parsed")
+            .expect("workspace found");
+
+        let names: Vec<_> = workspace
+            .packages()
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["my-core", "xtask"]);
+        assert_eq!(
+            workspace.package_for("crates/core/src/lib.rs"),
+            Some("my-core")
+        );
+        assert_eq!(workspace.package_for("README.md"), None);
+    }
+
+    #[test]
+    fn discover_workspace_in_a_subdirectory() {
+        let dir = tempdir().expect("temp dir obtained");
+        fs::create_dir(dir.path().join(".git")).expect("repository created");
+        let project = dir.path().join("rust");
+        write_manifest(
+            &project,
+            "[package]\nname = \"app\"\n\n[workspace]\nmembers = [\"crates/*\"]\n",
+        );
+        write_manifest(
+            &project.join("crates/core"),
+            "[package]\nname = \"my-core\"\n",
+        );
+
+        let workspace = Workspace::discover(&project)
+            .expect("manifest parsed")
+            .expect("workspace found");
+
+        // Files are named from the root of the repository
+        assert_eq!(
+            workspace.package_for("rust/crates/core/src/lib.rs"),
+            Some("my-core")
+        );
+        assert_eq!(workspace.package_for("rust/src/main.rs"), Some("app"));
+        assert_eq!(workspace.package_for("docs/index.md"), None);
+    }
+
+    #[test]
+    fn discover_workspace_at_a_revision() {
+        let files = HashMap::from([
+            ("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n"),
+            ("crates/core/Cargo.toml", "[package]\nname = \"my-core\"\n"),
+            ("crates/cli/Cargo.toml", "[package]\nname = \"my-cli\"\n"),
+        ]);
+        // Paths of the history may name files deleted since
+        let paths = ["crates/core/src/lib.rs", "crates/old/Cargo.toml"]
+            .into_iter()
+            .chain(files.keys().copied());
+
+        let workspace = Workspace::discover_at(paths, |path| {
+            Ok(files.get(path).map(|content| content.as_bytes().to_vec()))
+        })
+        .expect("manifest parsed")
+        .expect("workspace found");
+
+        let names: Vec<_> = workspace
+            .packages()
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["my-cli", "my-core"]);
+    }
+
+    #[test]
+    fn no_manifest_means_no_workspace() {
+        let dir = tempdir().expect("temp dir obtained");
+
+        assert!(Workspace::discover(dir.path()).expect("no error").is_none());
+    }
+}
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, path::Path};
 
-use quality_time::{get_metrics, Churn, MetricReader, RepositoryExplorer};
+use quality_time::{get_metrics, get_metrics_at, Churn, MetricReader, RepositoryExplorer};
 
 struct TestReader {}
 
@@ -8,6 +8,10 @@ impl MetricReader for TestReader {
     fn get_cyclomatic_from_path_and_content(&self, _path: &Path) -> Option<f64> {
         Some(1.0)
     }
+
+    fn get_cyclomatic_from_content(&self, _path: &Path, content: Vec<u8>) -> Option<f64> {
+        Some(content.len() as f64)
+    }
 }
 
 struct TestExplorer {}
@@ -16,6 +20,17 @@ impl RepositoryExplorer for TestExplorer {
     fn change_count_per_file(&self) -> eyre::Result<std::collections::HashMap<String, Churn>> {
         Ok(HashMap::from([("file".to_string(), Churn::from(1))]))
     }
+
+    fn change_count_per_file_until(&self, _revision: &str) -> eyre::Result<HashMap<String, Churn>> {
+        Ok(HashMap::from([
+            ("file".to_string(), Churn::from(1)),
+            ("deleted".to_string(), Churn::from(2)),
+        ]))
+    }
+
+    fn file_content(&self, revision: &str, path: &str) -> eyre::Result<Option<Vec<u8>>> {
+        Ok((path == "file").then(|| revision.as_bytes().to_vec()))
+    }
 }
 
 #[test]
@@ -24,3 +39,11 @@ fn list_metrics() {
 
     assert!(!metrics.file_metrics().is_empty())
 }
+
+#[test]
+fn list_metrics_at_revision() {
+    let metrics = get_metrics_at(&TestExplorer {}, &TestReader {}, "v1.0").expect("metrics");
+
+    assert_eq!(metrics.file_metrics().len(), 1);
+    assert_eq!(metrics.file_metrics()[0].complexity, 4.0);
+}